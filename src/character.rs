@@ -1,20 +1,55 @@
 //! A text character
 
-use types::{FontSize, Scalar};
+use types::{FontSize, Rectangle, Scalar};
 use ImageSize;
 
 /// Holds rendered character data.
+///
+/// The image of a character is a rectangle of its texture,
+/// which can be shared with other characters, such as a page of an atlas.
+/// Use `Character::new` for a character using a whole texture.
 #[derive(Clone)]
 pub struct Character<'a, T: 'a + ImageSize> {
     /// The offset of character.
     pub offset: [Scalar; 2],
     /// The size of character, including space.
     pub size: [Scalar; 2],
+    /// The offset of the character image in the texture, in pixels.
+    pub atlas_offset: [Scalar; 2],
+    /// The size of the character image in the texture, in pixels.
+    pub atlas_size: [Scalar; 2],
+    /// The size of the character image when drawn.
+    ///
+    /// This is the atlas size, unless the image is scaled,
+    /// such as a distance field rendered at another size.
+    pub image_size: [Scalar; 2],
     /// The texture of the character.
     pub texture: &'a T,
+    /// An id of the texture, the same for characters sharing it.
+    ///
+    /// Characters with the same id are drawn together,
+    /// so the id of a texture must not be reused while the cache holds characters using it.
+    /// Characters without an id are drawn one at a time.
+    pub texture_id: Option<usize>,
 }
 
 impl<'a, T: ImageSize> Character<'a, T> {
+    /// Creates a character drawn with the whole texture at its size,
+    /// without a texture id.
+    pub fn new(offset: [Scalar; 2], size: [Scalar; 2], texture: &'a T) -> Character<'a, T> {
+        let (w, h) = texture.get_size();
+        let texture_size = [w as Scalar, h as Scalar];
+        Character {
+            offset,
+            size,
+            atlas_offset: [0.0; 2],
+            atlas_size: texture_size,
+            image_size: texture_size,
+            texture,
+            texture_id: None,
+        }
+    }
+
     /// The left offset.
     pub fn left(&self) -> Scalar {
         self.offset[0]
@@ -36,6 +71,47 @@ impl<'a, T: ImageSize> Character<'a, T> {
     }
 }
 
+/// The vertical metrics of a font at some size.
+///
+/// Distances are measured up from the baseline,
+/// so the descent is usually negative.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FontMetrics {
+    /// The height of the highest glyphs above the baseline.
+    pub ascent: Scalar,
+    /// The depth of the lowest glyphs below the baseline, usually negative.
+    pub descent: Scalar,
+    /// The recommended extra space between lines.
+    pub line_gap: Scalar,
+}
+
+impl FontMetrics {
+    /// Returns the recommended distance between baselines.
+    pub fn line_height(&self) -> Scalar {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    /// Returns the height of a line, without the line gap.
+    pub fn height(&self) -> Scalar {
+        self.ascent - self.descent
+    }
+}
+
+/// The bounds of a line of text.
+///
+/// Rectangles use the coordinates of `Text::draw`,
+/// starting at the pen position on the baseline with y pointing down.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextBounds {
+    /// The tight bounds of the drawn glyphs.
+    ///
+    /// This is a zero sized rectangle at the origin when nothing is drawn.
+    pub ink: Rectangle,
+    /// The advance width times the line height,
+    /// with the top at the ascent of the font.
+    pub logical: Rectangle,
+}
+
 /// Stores characters in a buffer and loads them by demand.
 pub trait CharacterCache {
     /// The texture type associated with the character cache.
@@ -49,13 +125,140 @@ pub trait CharacterCache {
                      ch: char)
                      -> Result<Character<'a, Self::Texture>, Self::Error>;
 
+    /// Get reference to character for a pen position with a fraction of a pixel.
+    ///
+    /// Caches that render glyphs at several horizontal offsets within a pixel
+    /// return the glyph closest to the fraction of `x`,
+    /// with an offset that puts its image at a whole pixel when drawn at `x`.
+    /// The default implementation returns `character`.
+    fn subpixel_character<'a>(&'a mut self,
+                              font_size: FontSize,
+                              ch: char,
+                              _x: Scalar)
+                              -> Result<Character<'a, Self::Texture>, Self::Error> {
+        self.character(font_size, ch)
+    }
+
+    /// Whether the character images are signed distance fields.
+    ///
+    /// The alpha of a distance field is 0.5 on the outline of a glyph,
+    /// increasing inside and decreasing outside,
+    /// so it can be drawn sharp at any scale with `Graphics::tri_list_uv_sdf`.
+    /// The default implementation returns `false`.
+    fn is_distance_field(&self) -> bool {
+        false
+    }
+
+    /// The distance in texels from the outline of a glyph
+    /// to where the alpha of its distance field reaches 0 or 1.
+    ///
+    /// The default implementation returns 0.
+    fn distance_field_spread(&self) -> Scalar {
+        0.0
+    }
+
+    /// Get the vertical metrics of the font.
+    ///
+    /// The default implementation estimates the metrics from the font size,
+    /// with an ascent of 0.8 and a descent of 0.2 times the size.
+    fn metrics(&mut self, font_size: FontSize) -> Result<FontMetrics, Self::Error> {
+        let size = font_size as Scalar;
+        Ok(FontMetrics {
+            ascent: 0.8 * size,
+            descent: -0.2 * size,
+            line_gap: 0.0,
+        })
+    }
+
+    /// Get the adjustment of the distance between two characters.
+    ///
+    /// This is added to the advance of the first character
+    /// when it is followed by the second.
+    /// The default implementation returns zero.
+    fn kerning(&mut self,
+               _font_size: FontSize,
+               _first: char,
+               _second: char)
+               -> Result<Scalar, Self::Error> {
+        Ok(0.0)
+    }
+
+    /// Get the tight bounds of a character, relative to the pen position.
+    ///
+    /// Returns `None` for characters that draw nothing.
+    /// The default implementation returns the rectangle of the character image,
+    /// and `None` for whitespace.
+    fn ink_bounds(&mut self, font_size: FontSize, ch: char) -> Result<Option<Rectangle>, Self::Error> {
+        if ch.is_whitespace() {
+            return Ok(None);
+        }
+        let character = self.character(font_size, ch)?;
+        let [w, h] = character.image_size;
+        Ok(Some([character.left(), -character.top(), w, h]))
+    }
+
+    /// Return the ink and logical bounds of some given text on a single line.
+    fn measure(&mut self, size: FontSize, text: &str) -> Result<TextBounds, Self::Error> {
+        let metrics = self.metrics(size)?;
+        let mut x = 0.0;
+        let mut prev = None;
+        let mut ink: Option<[Scalar; 4]> = None;
+        for ch in text.chars() {
+            if let Some(prev) = prev {
+                x += self.kerning(size, prev, ch)?;
+            }
+            prev = Some(ch);
+            if let Some(r) = self.ink_bounds(size, ch)? {
+                let (x0, y0, x1, y1) = (x + r[0], r[1], x + r[0] + r[2], r[1] + r[3]);
+                ink = Some(match ink {
+                    Some(b) => [b[0].min(x0), b[1].min(y0), b[2].max(x1), b[3].max(y1)],
+                    None => [x0, y0, x1, y1],
+                });
+            }
+            x += self.character(size, ch)?.width();
+        }
+        let ink = ink.map(|b| [b[0], b[1], b[2] - b[0], b[3] - b[1]]).unwrap_or([0.0; 4]);
+        Ok(TextBounds {
+            ink,
+            logical: [0.0, -metrics.ascent, x, metrics.line_height()],
+        })
+    }
+
     /// Return the width for some given text.
     fn width(&mut self, size: FontSize, text: &str) -> Result<::math::Scalar, Self::Error> {
         let mut width = 0.0;
+        let mut prev = None;
         for ch in text.chars() {
+            if let Some(prev) = prev {
+                width += self.kerning(size, prev, ch)?;
+            }
             let character = self.character(size, ch)?;
             width += character.width();
+            prev = Some(ch);
         }
         Ok(width)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mock::Font;
+    use recorder::Texture;
+
+    #[test]
+    fn test_measure() {
+        // Characters 10 wide with glyphs 8 wide, 12 high, going 2 below the baseline.
+        let mut font = Font::new(Texture::new(0, 8, 12))
+            .offset([1.0, 10.0])
+            .atlas([0.0, 0.0, 8.0, 12.0], [8.0, 12.0])
+            .kerning('A', 'V', -3.0);
+        let bounds = font.measure(10, "a b ").unwrap();
+        assert_eq!(bounds.ink, [1.0, -10.0, 28.0, 12.0]);
+        assert_eq!(bounds.logical, [0.0, -8.0, 40.0, 10.0]);
+        let bounds = font.measure(10, " ").unwrap();
+        assert_eq!(bounds.ink, [0.0; 4]);
+        let bounds = font.measure(10, "AV").unwrap();
+        assert_eq!(bounds.logical[2], 17.0);
+    }
+}
@@ -0,0 +1,118 @@
+//! Draw polygon with multiple contours
+
+use types::Color;
+use {types, triangulation, Graphics, DrawState};
+use math::Matrix2d;
+
+/// The rule deciding which areas are inside a shape with multiple contours.
+///
+/// The rule is applied to the winding number of a point,
+/// which counts how many times the contours go around the point,
+/// with opposite sign for opposite directions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside when the winding number is not zero.
+    ///
+    /// Holes must go in the opposite direction of the outer contour.
+    NonZero,
+    /// Inside when the winding number is odd.
+    ///
+    /// Holes are cut out regardless of direction.
+    EvenOdd,
+}
+
+impl FillRule {
+    /// Returns true if a winding number is inside.
+    #[inline(always)]
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+/// A polygon with holes
+///
+/// Each contour is a closed polygon.
+/// Contours can overlap and intersect themselves.
+#[derive(Copy, Clone)]
+pub struct ComplexPolygon {
+    /// The color of the polygon
+    pub color: Color,
+    /// The fill rule
+    pub fill_rule: FillRule,
+}
+
+impl ComplexPolygon {
+    /// Creates new polygon using the even-odd fill rule
+    pub fn new(color: Color) -> ComplexPolygon {
+        ComplexPolygon {
+            color,
+            fill_rule: FillRule::EvenOdd,
+        }
+    }
+
+    /// Sets color.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets fill rule.
+    pub fn fill_rule(mut self, value: FillRule) -> Self {
+        self.fill_rule = value;
+        self
+    }
+
+    /// Draws polygon using the default method.
+    #[inline(always)]
+    pub fn draw<G>(&self,
+                   contours: types::Polygons,
+                   draw_state: &DrawState,
+                   transform: Matrix2d,
+                   g: &mut G)
+        where G: Graphics
+    {
+        g.complex_polygon(self, contours, draw_state, transform);
+    }
+
+    /// Draws polygon using triangulation.
+    pub fn draw_tri<G>(&self,
+                       contours: types::Polygons,
+                       draw_state: &DrawState,
+                       transform: Matrix2d,
+                       g: &mut G)
+        where G: Graphics
+    {
+        if self.color[3] == 0.0 {
+            return;
+        }
+        g.tri_list(draw_state, &self.color, |f| {
+            triangulation::with_contours_tri_list(self.fill_rule,
+                                                  transform,
+                                                  contours,
+                                                  |vertices| f(vertices))
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_complex_polygon() {
+        let _polygon = ComplexPolygon::new([1.0; 4])
+            .color([0.0; 4])
+            .fill_rule(FillRule::NonZero);
+    }
+
+    #[test]
+    fn test_fill_rule() {
+        assert!(FillRule::NonZero.is_inside(2));
+        assert!(!FillRule::EvenOdd.is_inside(2));
+        assert!(FillRule::EvenOdd.is_inside(-1));
+        assert!(!FillRule::NonZero.is_inside(0));
+    }
+}
@@ -1,8 +1,8 @@
 //! Least square deforming of a 2D grid.
 
-use types::Rectangle;
+use types::{Color, Rectangle};
 use {Line, Graphics, DrawState};
-use triangulation::{tx, ty};
+use triangulation::{self, tx, ty};
 use math::{Matrix2d, Scalar, Vec2d};
 
 /// Represents a deformed grid.
@@ -20,6 +20,10 @@ pub struct DeformGrid {
     pub indices: Vec<usize>,
     /// The texture coordinates.
     pub texture_coords: Vec<[f32; 2]>,
+    /// The vertex colors, multiplied with the texture color.
+    ///
+    /// The colors are white by default.
+    pub colors: Vec<Color>,
     /// Initial position of control points.
     pub ps: Vec<[Scalar; 2]>,
     /// The current position of control points.
@@ -68,6 +72,7 @@ impl DeformGrid {
             rect: rect,
             vertices: vertices,
             indices: indices,
+            colors: vec![[1.0; 4]; texture_coords.len()],
             texture_coords: texture_coords,
             ps: Vec::new(),
             qs: Vec::new(),
@@ -190,30 +195,44 @@ impl DeformGrid {
         use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
 
         let mat = transform;
-        let color = [1.0; 4];
-        let a = color[3];
-        if a == 0.0 {
-            return;
-        }
         let mut vertices: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
         let mut uvs: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
-        let mut offset = 0;
-        for &ind in self.indices.iter() {
-            if offset >= BUFFER_SIZE {
-                g.tri_list_uv(&draw_state, &color, texture, |f| f(&vertices, &uvs));
-                offset = 0;
-            }
-            let vert = self.vertices[ind];
-            vertices[offset] = [tx(mat, vert[0], vert[1]), ty(mat, vert[0], vert[1])];
-            uvs[offset] = self.texture_coords[ind];
-            offset += 1;
-        }
-        if offset > 0 {
-            g.tri_list_uv(&draw_state,
-                          &color,
-                          texture,
-                          |f| f(&vertices[..offset], &uvs[..offset]));
+        let color = self.colors.first().cloned().unwrap_or([1.0; 4]);
+        if self.colors.iter().all(|&c| c == color) {
+            // Share vertices between triangles when there is one color.
+            g.tri_list_uv_indexed(draw_state, &color, texture, |f| {
+                triangulation::chunk_indexed_tri_list(self.vertices.len(),
+                                                      &self.indices,
+                                                      |ids, indices| {
+                    for ((v, uv), &ind) in vertices.iter_mut().zip(uvs.iter_mut()).zip(ids) {
+                        let vert = self.vertices[ind];
+                        *v = [tx(mat, vert[0], vert[1]), ty(mat, vert[0], vert[1])];
+                        *uv = self.texture_coords[ind];
+                    }
+                    f(&vertices[..ids.len()], &uvs[..ids.len()], indices)
+                })
+            });
+            return;
         }
+
+        let mut colors: [[f32; 4]; BUFFER_SIZE] = [[0.0; 4]; BUFFER_SIZE];
+        g.tri_list_uv_c(draw_state, texture, |f| {
+            let mut offset = 0;
+            for &ind in self.indices.iter() {
+                if offset >= BUFFER_SIZE {
+                    f(&vertices, &uvs, &colors);
+                    offset = 0;
+                }
+                let vert = self.vertices[ind];
+                vertices[offset] = [tx(mat, vert[0], vert[1]), ty(mat, vert[0], vert[1])];
+                uvs[offset] = self.texture_coords[ind];
+                colors[offset] = self.colors[ind];
+                offset += 1;
+            }
+            if offset > 0 {
+                f(&vertices[..offset], &uvs[..offset], &colors[..offset]);
+            }
+        });
     }
 
     /// Adds a control point, in original coordinates.
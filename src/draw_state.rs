@@ -2,6 +2,7 @@
 
 /// Graphics draw state used for blending, clipping and stencil rendering.
 #[derive(Copy, Clone, PartialEq, Debug, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct DrawState {
     /// Scissor mask to use. If set, no pixel outside of this
     /// rectangle (in screen space) will be written to as a result of rendering.
@@ -76,6 +77,7 @@ impl DrawState {
 /// Using presets since some backends need one pipeline state object instance
 /// per blending technique.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Blend {
     /// Alpha blending (allows semi-transparent pixels).
     ///
@@ -115,6 +117,7 @@ pub enum Blend {
 
 /// Stencil buffer settings.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Stencil {
     /// Draw to stencil buffer.
     Clip(u8),
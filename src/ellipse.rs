@@ -2,7 +2,10 @@
 
 use types::{Color, Radius, Rectangle, Resolution};
 use {triangulation, DrawState, Graphics};
-use math::Matrix2d;
+use math::{identity, Matrix2d};
+use gradient::Gradient;
+use line::{Dash, Shape};
+use polyline::Join;
 
 pub use rectangle::centered;
 pub use rectangle::centered_square as circle;
@@ -14,6 +17,8 @@ pub struct Border {
     pub color: Color,
     /// The border radius
     pub radius: Radius,
+    /// The dash pattern, where each dash has square ends
+    pub dash: Option<Dash>,
 }
 
 /// An ellipse with filled color
@@ -44,6 +49,7 @@ impl Ellipse {
             border: Some(Border {
                 color: color,
                 radius: radius,
+                dash: None,
             }),
             resolution: 128,
         }
@@ -85,6 +91,30 @@ impl Ellipse {
         g.ellipse(self, rectangle, draw_state, transform);
     }
 
+    /// Draws ellipse filled with a gradient instead of the color.
+    ///
+    /// The gradient is in the same coordinates as the ellipse.
+    /// The border is drawn using the default method.
+    pub fn draw_gradient<R: Into<Rectangle>, G>(&self,
+                                                gradient: &Gradient,
+                                                rectangle: R,
+                                                draw_state: &DrawState,
+                                                transform: Matrix2d,
+                                                g: &mut G)
+        where G: Graphics
+    {
+        let rectangle = rectangle.into();
+        let mut triangles = vec![];
+        triangulation::with_ellipse_tri_list(self.resolution,
+                                             identity(),
+                                             rectangle,
+                                             |vertices| triangles.extend_from_slice(vertices));
+        g.tri_list_c(draw_state, |f| {
+            gradient.stream_tri_list_c(transform, &triangles, |vertices, colors| f(vertices, colors))
+        });
+        self.draw_border(rectangle, draw_state, transform, g);
+    }
+
     /// Draws ellipse using triangulation.
     pub fn draw_tri<R: Into<Rectangle>, G>(&self,
                                            rectangle: R,
@@ -100,8 +130,35 @@ impl Ellipse {
                                                  rectangle,
                                                  |vertices| f(vertices))
         });
+        self.draw_border(rectangle, draw_state, transform, g);
+    }
 
-        if let Some(Border { color, radius: border_radius }) = self.border {
+    /// Draws the border using triangulation.
+    fn draw_border<G>(&self,
+                      rectangle: Rectangle,
+                      draw_state: &DrawState,
+                      transform: Matrix2d,
+                      g: &mut G)
+        where G: Graphics
+    {
+        if let Some(Border { color, radius: border_radius, dash: Some(dash) }) = self.border {
+            let outline = triangulation::ellipse_outline(self.resolution, rectangle);
+            g.tri_list(draw_state, &color, |f| {
+                for points in dash.split(&outline, true) {
+                    // A dash without gaps around the outline ends where it starts.
+                    let n = points.len();
+                    let closed = n > 2 && points[0] == points[n - 1];
+                    let points = if closed { &points[..n - 1] } else { &points[..] };
+                    triangulation::with_polyline_tri_list(Shape::Square,
+                                                          Join::Miter(4.0),
+                                                          closed,
+                                                          transform,
+                                                          points,
+                                                          border_radius,
+                                                          |vertices| f(vertices))
+                }
+            });
+        } else if let Some(Border { color, radius: border_radius, .. }) = self.border {
             g.tri_list(&draw_state, &color, |f| {
                 triangulation::with_ellipse_border_tri_list(self.resolution,
                                                             transform,
@@ -124,6 +181,30 @@ mod test {
             .border(Border {
                 color: [1.0; 4],
                 radius: 3.0,
+                dash: Some(Dash::new(&[2.0, 1.0])),
             });
     }
+
+    #[test]
+    fn test_draw_gradient() {
+        use recorder::{Command, Recorder};
+        use Context;
+
+        let c = Context::new_abs(100.0, 100.0);
+        let gradient = Gradient::linear([0.0, 0.0], [10.0, 0.0])
+            .stop(0.0, [1.0, 0.0, 0.0, 1.0])
+            .stop(1.0, [0.0, 0.0, 1.0, 1.0]);
+        let kinds = |ellipse: Ellipse| -> Vec<&'static str> {
+            let mut recorder = Recorder::new();
+            ellipse.draw_gradient(&gradient, [0.0, 0.0, 10.0, 10.0], &c.draw_state, c.transform, &mut recorder);
+            recorder.commands.iter().map(|command| match *command {
+                Command::TriListC { .. } => "gradient",
+                Command::TriList { color, .. } if color == [1.0; 4] => "border",
+                _ => panic!("unexpected command"),
+            }).collect()
+        };
+        // Only the gradient covers the inside.
+        assert_eq!(kinds(Ellipse::new([0.5; 4])), vec!["gradient"]);
+        assert_eq!(kinds(Ellipse::new_border([1.0; 4], 1.0)), vec!["gradient", "border"]);
+    }
 }
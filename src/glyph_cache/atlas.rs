@@ -0,0 +1,213 @@
+//! Packing of glyph images into shared pages.
+//!
+//! Glyphs are placed on shelves, rows of glyphs with similar height.
+//! A page doubles in size when it is full, up to the maximum size,
+//! and then a new page is started.
+//!
+//! The atlas stores alpha values, and keeps the changed rectangle of every page,
+//! so a character cache knows which parts of the textures to upload.
+
+/// A square page of an atlas
+pub struct Page {
+    /// The width and height in pixels.
+    pub size: u32,
+    /// The alpha values, row by row.
+    pub data: Vec<u8>,
+    /// The rectangle `[x, y, width, height]` that changed since the page was last uploaded.
+    ///
+    /// This is the whole page when it is new or has grown.
+    pub dirty: Option<[u32; 4]>,
+    shelves: Vec<Shelf>,
+}
+
+/// A row of images with the same maximum height.
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// The left edge of the free space.
+    x: u32,
+}
+
+impl Page {
+    /// Creates a new empty page.
+    pub fn new(size: u32) -> Page {
+        Page {
+            size,
+            data: vec![0; (size * size) as usize],
+            dirty: Some([0, 0, size, size]),
+            shelves: vec![],
+        }
+    }
+
+    /// Copies the image at an offset.
+    pub fn image(&self, offset: [u32; 2], width: u32, height: u32) -> Vec<u8> {
+        let mut image = Vec::with_capacity((width * height) as usize);
+        for y in offset[1]..offset[1] + height {
+            let start = (y * self.size + offset[0]) as usize;
+            image.extend_from_slice(&self.data[start..start + width as usize]);
+        }
+        image
+    }
+
+    /// Finds space for an image, returning its offset.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        let size = self.size;
+        // Use the lowest shelf with room, to waste the least space.
+        let best = self.shelves.iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.x + width <= size)
+            .min_by_key(|shelf| shelf.height);
+        if let Some(shelf) = best {
+            let offset = [shelf.x, shelf.y];
+            shelf.x += width;
+            return Some(offset);
+        }
+        let y = self.shelves.last().map(|shelf| shelf.y + shelf.height).unwrap_or(0);
+        if width > size || y + height > size {
+            return None;
+        }
+        self.shelves.push(Shelf { y, height, x: width });
+        Some([0, y])
+    }
+
+    /// Doubles the size of the page, keeping images at their offsets.
+    fn grow(&mut self) {
+        let size = 2 * self.size;
+        let mut data = vec![0; (size * size) as usize];
+        for (src, dst) in self.data.chunks(self.size as usize).zip(data.chunks_mut(size as usize)) {
+            dst[..src.len()].copy_from_slice(src);
+        }
+        self.size = size;
+        self.data = data;
+        self.dirty = Some([0, 0, size, size]);
+    }
+
+    /// Copies an image to an offset.
+    fn blit(&mut self, offset: [u32; 2], width: u32, data: &[u8]) {
+        if width == 0 {
+            return;
+        }
+        for (y, row) in data.chunks(width as usize).enumerate() {
+            let start = ((offset[1] + y as u32) * self.size + offset[0]) as usize;
+            self.data[start..start + row.len()].copy_from_slice(row);
+        }
+        let height = (data.len() / width as usize) as u32;
+        let (x0, y0) = (offset[0], offset[1]);
+        let (x1, y1) = (x0 + width, y0 + height);
+        self.dirty = Some(match self.dirty {
+            Some([x, y, w, h]) => {
+                let (dx0, dy0) = (x.min(x0), y.min(y0));
+                [dx0, dy0, (x + w).max(x1) - dx0, (y + h).max(y1) - dy0]
+            }
+            None => [x0, y0, width, height],
+        });
+    }
+}
+
+/// Pages of packed images
+pub struct Atlas {
+    /// The size of new pages.
+    pub initial_size: u32,
+    /// The size pages can grow to.
+    pub max_size: u32,
+    /// The pages.
+    pub pages: Vec<Page>,
+}
+
+impl Atlas {
+    /// Creates a new atlas without pages.
+    pub fn new(initial_size: u32, max_size: u32) -> Atlas {
+        Atlas {
+            initial_size,
+            max_size: max_size.max(initial_size),
+            pages: vec![],
+        }
+    }
+
+    /// Returns the number of texels in all pages.
+    pub fn area(&self) -> usize {
+        self.pages.iter().map(|page| (page.size * page.size) as usize).sum()
+    }
+
+    /// Adds an image of alpha values, returning its page and offset.
+    ///
+    /// Images larger than the maximum size get a page of their own.
+    pub fn insert(&mut self, width: u32, height: u32, data: &[u8]) -> (usize, [u32; 2]) {
+        let (page, offset) = self.allocate(width, height);
+        self.pages[page].blit(offset, width, data);
+        (page, offset)
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> (usize, [u32; 2]) {
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some(offset) = page.allocate(width, height) {
+                return (i, offset);
+            }
+        }
+        let max_size = self.max_size;
+        if let Some(page) = self.pages.last_mut() {
+            while page.size < max_size {
+                page.grow();
+                if let Some(offset) = page.allocate(width, height) {
+                    return (self.pages.len() - 1, offset);
+                }
+            }
+        }
+        let mut size = self.initial_size.max(1);
+        while size < width || size < height {
+            size *= 2;
+        }
+        let mut page = Page::new(size);
+        let offset = page.allocate(width, height).expect("page is large enough");
+        self.pages.push(page);
+        (self.pages.len() - 1, offset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let mut atlas = Atlas::new(16, 32);
+        let mut rects = vec![];
+        for i in 0..40 {
+            let (w, h) = (3 + i % 5, 2 + i % 7);
+            let data = vec![i as u8 + 1; (w * h) as usize];
+            let (page, offset) = atlas.insert(w, h, &data);
+            rects.push((page, offset, w, h, i as u8 + 1));
+        }
+        // Pages grow before new pages are added.
+        assert_eq!(atlas.pages[0].size, 32);
+        assert!(atlas.pages.len() > 1);
+        for &(page, [x, y], w, h, value) in &rects {
+            let page = &atlas.pages[page];
+            assert!(x + w <= page.size && y + h <= page.size);
+            assert_eq!(page.image([x, y], w, h), vec![value; (w * h) as usize]);
+        }
+    }
+
+    #[test]
+    fn test_dirty() {
+        let mut atlas = Atlas::new(16, 32);
+        atlas.insert(4, 2, &[1; 8]);
+        assert_eq!(atlas.pages[0].dirty, Some([0, 0, 16, 16]));
+        atlas.pages[0].dirty = None;
+        atlas.insert(3, 2, &[1; 6]);
+        assert_eq!(atlas.pages[0].dirty, Some([4, 0, 3, 2]));
+        atlas.insert(2, 5, &[1; 10]);
+        assert_eq!(atlas.pages[0].dirty, Some([0, 0, 7, 7]));
+        // Growing changes the whole page.
+        atlas.pages[0].dirty = None;
+        atlas.insert(16, 16, &[1; 256]);
+        assert_eq!(atlas.pages[0].dirty, Some([0, 0, 32, 32]));
+    }
+
+    #[test]
+    fn test_large() {
+        let mut atlas = Atlas::new(16, 32);
+        let (page, offset) = atlas.insert(40, 3, &[1; 120]);
+        assert_eq!((page, offset), (0, [0, 0]));
+        assert_eq!(atlas.pages[0].size, 64);
+    }
+}
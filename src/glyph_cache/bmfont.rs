@@ -0,0 +1,466 @@
+//! Bitmap fonts in the AngelCode BMFont format.
+//!
+//! A font is a text descriptor file with pre-rendered pages.
+//! Pages should have white glyphs on a transparent background,
+//! so the color of `Text` applies.
+//!
+//! `BitmapFont::new` reads pages in the uncompressed TGA format,
+//! and `BitmapFont::from_descriptor` takes a function to decode other formats.
+
+use texture::{CreateTexture, Format, TextureSettings};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use ImageSize;
+use types::{FontSize, Rectangle, Scalar};
+use character::{Character, CharacterCache, FontMetrics};
+
+/// An error loading a bitmap font
+#[derive(Debug)]
+pub enum Error<E> {
+    /// A file could not be read.
+    Io(io::Error),
+    /// The descriptor is invalid.
+    Parse {
+        /// The line number, starting at 1.
+        line: usize,
+        /// What is wrong.
+        message: String,
+    },
+    /// A page texture could not be created.
+    Texture(E),
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "{}", err),
+            Error::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+            Error::Texture(ref err) => write!(f, "texture error: {:?}", err),
+        }
+    }
+}
+
+impl<E> From<io::Error> for Error<E> {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A glyph in a page.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Glyph {
+    page: usize,
+    // The position and size in the page.
+    rect: Rectangle,
+    // The offset from the top of the line.
+    offset: [Scalar; 2],
+    advance: Scalar,
+}
+
+/// A font with pre-rendered glyphs
+///
+/// The font size is ignored, since the glyphs have a fixed size.
+/// Scale the transform to draw larger text.
+pub struct BitmapFont<T> {
+    /// The distance between baselines.
+    pub line_height: Scalar,
+    /// The distance from the top of a line to the baseline.
+    pub base: Scalar,
+    /// The page textures.
+    pub pages: Vec<T>,
+    chars: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), Scalar>,
+    // The glyph for missing characters.
+    missing: Glyph,
+}
+
+impl<T> BitmapFont<T> {
+    /// Loads a font from a descriptor file in the text format.
+    ///
+    /// The pages are read relative to the descriptor,
+    /// and must be uncompressed TGA files.
+    pub fn new<P, F>(path: P,
+                     factory: &mut F,
+                     settings: &TextureSettings)
+                     -> Result<BitmapFont<T>, Error<T::Error>>
+        where P: AsRef<Path>,
+              T: CreateTexture<F>
+    {
+        let path = path.as_ref();
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        BitmapFont::from_descriptor(&text, factory, settings, |file| {
+            let mut data = vec![];
+            File::open(dir.join(file))?.read_to_end(&mut data)?;
+            decode_tga(&data)
+        })
+    }
+
+    /// Loads a font from a descriptor in the text format.
+    ///
+    /// The `decode` function reads the page with a file name,
+    /// and returns its size and RGBA pixels, row by row from the top.
+    /// Pixels not matching the size and glyphs outside their page are errors.
+    pub fn from_descriptor<F, D>(text: &str,
+                                 factory: &mut F,
+                                 settings: &TextureSettings,
+                                 mut decode: D)
+                                 -> Result<BitmapFont<T>, Error<T::Error>>
+        where T: CreateTexture<F>,
+              D: FnMut(&str) -> io::Result<([u32; 2], Vec<u8>)>
+    {
+        let mut line_height = None;
+        let mut base = 0.0;
+        let mut page_files: Vec<(usize, String)> = vec![];
+        let mut chars = HashMap::new();
+        let mut kernings = HashMap::new();
+        let mut replacement = None;
+        for (i, line) in text.lines().enumerate() {
+            let (tag, attributes) = parse_line(line);
+            let line = i + 1;
+            let get = |key: &str| -> Result<&str, Error<T::Error>> {
+                attributes.iter()
+                    .find(|attribute| attribute.0 == key)
+                    .map(|attribute| attribute.1)
+                    .ok_or_else(|| Error::Parse { line, message: format!("missing `{}`", key) })
+            };
+            let number = |key: &str| -> Result<i64, Error<T::Error>> {
+                let value = get(key)?;
+                value.parse().map_err(|_| {
+                    Error::Parse { line, message: format!("invalid number `{}`", value) }
+                })
+            };
+            match tag {
+                "common" => {
+                    line_height = Some(number("lineHeight")? as Scalar);
+                    base = number("base")? as Scalar;
+                }
+                "page" => page_files.push((number("id")? as usize, get("file")?.to_string())),
+                "char" => {
+                    let glyph = Glyph {
+                        page: number("page")? as usize,
+                        rect: [number("x")? as Scalar,
+                               number("y")? as Scalar,
+                               number("width")? as Scalar,
+                               number("height")? as Scalar],
+                        offset: [number("xoffset")? as Scalar, number("yoffset")? as Scalar],
+                        advance: number("xadvance")? as Scalar,
+                    };
+                    let id = number("id")?;
+                    match ::std::char::from_u32(id as u32) {
+                        // BMFont uses -1 for the replacement of missing characters.
+                        _ if id < 0 => replacement = Some(glyph),
+                        Some(ch) => {
+                            chars.insert(ch, glyph);
+                        }
+                        None => {
+                            return Err(Error::Parse {
+                                line,
+                                message: format!("invalid character `{}`", id),
+                            })
+                        }
+                    }
+                }
+                "kerning" => {
+                    let first = ::std::char::from_u32(number("first")? as u32);
+                    let second = ::std::char::from_u32(number("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        kernings.insert((first, second), number("amount")? as Scalar);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let line_height = line_height.ok_or(Error::Parse {
+            line: 0,
+            message: "missing `common` line".to_string(),
+        })?;
+        if page_files.is_empty() {
+            return Err(Error::Parse { line: 0, message: "no pages".to_string() });
+        }
+
+        page_files.sort_by_key(|page| page.0);
+        // Glyphs refer to pages by id, which is used as the index of the texture.
+        if let Some((i, _)) = page_files.iter().enumerate().find(|&(i, page)| page.0 != i) {
+            return Err(Error::Parse { line: 0, message: format!("missing page {}", i) });
+        }
+        let missing = replacement
+            .or_else(|| chars.get(&'\u{FFFD}').cloned())
+            .or_else(|| chars.get(&'?').cloned())
+            .unwrap_or(Glyph {
+                page: 0,
+                rect: [0.0; 4],
+                offset: [0.0; 2],
+                advance: 0.0,
+            });
+        if let Some(glyph) = chars.values().chain(Some(&missing))
+            .find(|glyph| glyph.page >= page_files.len())
+        {
+            return Err(Error::Parse {
+                line: 0,
+                message: format!("missing page {}", glyph.page),
+            });
+        }
+
+        let mut images = Vec::with_capacity(page_files.len());
+        for (_, file) in page_files {
+            let (size, pixels) = decode(&file)?;
+            let len = (size[0] as usize).checked_mul(size[1] as usize).and_then(|n| n.checked_mul(4));
+            if len != Some(pixels.len()) {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("page `{}` has {} bytes for {}x{} pixels",
+                            file, pixels.len(), size[0], size[1]))));
+            }
+            images.push((size, pixels));
+        }
+        let glyphs = chars.iter().map(|(ch, glyph)| (format!("`{}`", ch), glyph))
+            .chain(Some(("the replacement".to_string(), &missing)));
+        for (name, glyph) in glyphs {
+            let [w, h] = images[glyph.page].0;
+            let r = glyph.rect;
+            if r[0] < 0.0 || r[1] < 0.0 || r[2] < 0.0 || r[3] < 0.0 ||
+               r[0] + r[2] > w as Scalar || r[1] + r[3] > h as Scalar {
+                return Err(Error::Parse {
+                    line: 0,
+                    message: format!("the glyph of {} is outside page {}", name, glyph.page),
+                });
+            }
+        }
+
+        let mut pages = Vec::with_capacity(images.len());
+        for (size, pixels) in images {
+            let texture = CreateTexture::create(factory, Format::Rgba8, &pixels, size, settings)
+                .map_err(Error::Texture)?;
+            pages.push(texture);
+        }
+        Ok(BitmapFont {
+            line_height,
+            base,
+            pages,
+            chars,
+            kernings,
+            missing,
+        })
+    }
+
+    /// Returns true if the font has a glyph for a character.
+    pub fn contains(&self, ch: char) -> bool {
+        self.chars.contains_key(&ch)
+    }
+
+    fn glyph(&self, ch: char) -> Glyph {
+        self.chars.get(&ch).cloned().unwrap_or(self.missing)
+    }
+}
+
+impl<T: ImageSize> CharacterCache for BitmapFont<T> {
+    type Texture = T;
+    type Error = ();
+
+    fn character<'a>(&'a mut self,
+                     _font_size: FontSize,
+                     ch: char)
+                     -> Result<Character<'a, T>, ()> {
+        let glyph = self.glyph(ch);
+        Ok(Character {
+            offset: [glyph.offset[0], self.base - glyph.offset[1]],
+            size: [glyph.advance, 0.0],
+            atlas_offset: [glyph.rect[0], glyph.rect[1]],
+            atlas_size: [glyph.rect[2], glyph.rect[3]],
+            image_size: [glyph.rect[2], glyph.rect[3]],
+            texture: &self.pages[glyph.page],
+            texture_id: Some(glyph.page),
+        })
+    }
+
+    fn kerning(&mut self, _font_size: FontSize, first: char, second: char) -> Result<Scalar, ()> {
+        Ok(self.kernings.get(&(first, second)).cloned().unwrap_or(0.0))
+    }
+
+    fn ink_bounds(&mut self, _font_size: FontSize, ch: char) -> Result<Option<Rectangle>, ()> {
+        let glyph = self.glyph(ch);
+        if glyph.rect[2] == 0.0 || glyph.rect[3] == 0.0 {
+            return Ok(None);
+        }
+        Ok(Some([glyph.offset[0], glyph.offset[1] - self.base, glyph.rect[2], glyph.rect[3]]))
+    }
+
+    fn metrics(&mut self, _font_size: FontSize) -> Result<FontMetrics, ()> {
+        Ok(FontMetrics {
+            ascent: self.base,
+            descent: self.base - self.line_height,
+            line_gap: 0.0,
+        })
+    }
+}
+
+/// Splits a line into a tag and `key=value` attributes.
+///
+/// Values can be quoted to contain spaces.
+fn parse_line(line: &str) -> (&str, Vec<(&str, &str)>) {
+    let line = line.trim();
+    let (tag, mut rest) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], &line[i..]),
+        None => (line, ""),
+    };
+    let mut attributes = vec![];
+    loop {
+        rest = rest.trim_start();
+        let eq = match rest.find('=') {
+            Some(eq) => eq,
+            None => break,
+        };
+        let key = rest[..eq].trim();
+        rest = &rest[eq + 1..];
+        let value = if rest.starts_with('"') {
+            let end = rest[1..].find('"').map(|i| i + 1).unwrap_or(rest.len());
+            let value = &rest[1..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+            value
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        attributes.push((key, value));
+    }
+    (tag, attributes)
+}
+
+/// Decodes an uncompressed TGA image into RGBA pixels.
+///
+/// Grayscale images become white with the gray level as alpha.
+pub fn decode_tga(data: &[u8]) -> io::Result<([u32; 2], Vec<u8>)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    if data.len() < 18 {
+        return Err(invalid("TGA header is too short"));
+    }
+    let id_length = data[0] as usize;
+    // `div_ceil` needs Rust 1.73.
+    #[allow(clippy::manual_div_ceil)]
+    let color_map_length = (data[5] as usize | (data[6] as usize) << 8) * ((data[7] as usize + 7) / 8);
+    let image_type = data[2];
+    let width = data[12] as usize | (data[13] as usize) << 8;
+    let height = data[14] as usize | (data[15] as usize) << 8;
+    let bits = data[16];
+    let top_down = data[17] & 0x20 != 0;
+    let bytes = match (image_type, bits) {
+        (2, 24) => 3,
+        (2, 32) => 4,
+        (3, 8) => 1,
+        _ => return Err(invalid("only uncompressed 8, 24 and 32 bit TGA images are supported")),
+    };
+    let start = 18 + id_length + color_map_length;
+    let row = width * bytes;
+    let end = row.checked_mul(height).and_then(|size| size.checked_add(start));
+    if end.filter(|&end| end <= data.len()).is_none() {
+        return Err(invalid("TGA image data is too short"));
+    }
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let y = if top_down { y } else { height - 1 - y };
+        let src = &data[start + y * row..start + (y + 1) * row];
+        for px in src.chunks(bytes) {
+            pixels.extend_from_slice(&match bytes {
+                1 => [255, 255, 255, px[0]],
+                3 => [px[2], px[1], px[0], 255],
+                _ => [px[2], px[1], px[0], px[3]],
+            });
+        }
+    }
+    Ok(([width as u32, height as u32], pixels))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use software;
+
+    const FONT: &str = r#"
+info face="Tiny Font" size=8 bold=0 italic=0 charset="" unicode=1 padding=0,0,0,0 spacing=1,1
+common lineHeight=10 base=8 scaleW=4 scaleH=2 pages=1 packed=0
+page id=0 file="tiny font.tga"
+chars count=3
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3 page=0 chnl=15
+char id=65 x=0 y=0 width=2 height=2 xoffset=1 yoffset=6 xadvance=4 page=0 chnl=15
+char id=86 x=2 y=0 width=2 height=2 xoffset=0 yoffset=7 xadvance=4 page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-1
+"#;
+
+    fn load() -> BitmapFont<software::Texture> {
+        BitmapFont::from_descriptor(FONT, &mut (), &TextureSettings::new(), |file| {
+            assert_eq!(file, "tiny font.tga");
+            Ok(([4, 2], vec![255; 32]))
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (tag, attributes) = parse_line(r#"info face="Tiny Font" size=8 padding=0,0"#);
+        assert_eq!(tag, "info");
+        assert_eq!(attributes, vec![("face", "Tiny Font"), ("size", "8"), ("padding", "0,0")]);
+    }
+
+    #[test]
+    fn test_character() {
+        let mut font = load();
+        {
+            let a = font.character(12, 'A').unwrap();
+            assert_eq!(a.offset, [1.0, 2.0]);
+            assert_eq!(a.width(), 4.0);
+            assert_eq!(a.atlas_offset, [0.0, 0.0]);
+            assert_eq!(a.atlas_size, [2.0, 2.0]);
+        }
+        assert_eq!(font.kerning(12, 'A', 'V'), Ok(-1.0));
+        assert_eq!(font.width(12, "AV A"), Ok(14.0));
+        assert_eq!(font.ink_bounds(12, 'V'), Ok(Some([0.0, -1.0, 2.0, 2.0])));
+        assert_eq!(font.ink_bounds(12, ' '), Ok(None));
+        assert_eq!(font.metrics(12).unwrap().line_height(), 10.0);
+        // Missing characters are replaced by `?`, or nothing.
+        assert_eq!(font.character(12, 'x').unwrap().width(), 0.0);
+    }
+
+    #[test]
+    fn test_decode_tga() {
+        // A 2x2 bottom-up BGR image.
+        let mut data = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0];
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        let (size, pixels) = decode_tga(&data).unwrap();
+        assert_eq!(size, [2, 2]);
+        assert_eq!(pixels, vec![9, 8, 7, 255, 12, 11, 10, 255, 3, 2, 1, 255, 6, 5, 4, 255]);
+        assert!(decode_tga(&data[..20]).is_err());
+        // A 65535x65535 image with a large color map and no data.
+        let header = [0, 1, 2, 0, 0, 255, 255, 32, 0, 0, 0, 0, 255, 255, 255, 255, 32, 0];
+        assert!(decode_tga(&header).is_err());
+    }
+
+    #[test]
+    fn test_bad_pages() {
+        let parse = |descriptor: &str| {
+            BitmapFont::<software::Texture>::from_descriptor(
+                descriptor, &mut (), &TextureSettings::new(), |_| Ok(([4, 2], vec![255; 32])))
+        };
+        assert!(parse(FONT).is_ok());
+        // Page ids are indices, so they can not have gaps.
+        let gap = FONT.replace("page id=0", "page id=0 file=\"a.tga\"\npage id=2");
+        assert!(parse(&gap).is_err());
+        // The replacement glyph is checked like the others.
+        let replacement = FONT.replace("chars count=3",
+            "char id=-1 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3 page=1 chnl=15");
+        assert!(parse(&replacement).is_err());
+        // Glyphs must be within their page.
+        let outside = FONT.replace("x=2 y=0 width=2", "x=3 y=0 width=2");
+        assert!(parse(&outside).is_err());
+        // The pixels must match the size of the page.
+        let short = BitmapFont::<software::Texture>::from_descriptor(
+            FONT, &mut (), &TextureSettings::new(), |_| Ok(([4, 2], vec![255; 31])));
+        assert!(short.is_err());
+    }
+}
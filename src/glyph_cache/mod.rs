@@ -11,6 +11,19 @@
 //! version = "*"
 //! features = ["glyph_cache_rusttype"]
 //! ```
+//!
+//! ### AngelCode BMFont
+//!
+//! Add the following to "Cargo.toml":
+//!
+//! ```ignore
+//! [dependencies.piston2d-graphics]
+//! version = "*"
+//! features = ["glyph_cache_bmfont"]
+//! ```
 
 #[cfg(feature = "glyph_cache_rusttype")]
 pub mod rusttype;
+#[cfg(feature = "glyph_cache_bmfont")]
+pub mod bmfont;
+pub mod atlas;
@@ -1,8 +1,8 @@
 //! Glyph caching using the RustType library.
 
 extern crate rusttype;
-use texture::{ops, CreateTexture, Format, TextureSettings};
-use std::collections::HashMap;
+use texture::{ops, CreateTexture, Format, TextureSettings, UpdateTexture};
+use std::collections::{BTreeMap, HashMap};
 
 extern crate fnv;
 use self::fnv::FnvHasher;
@@ -14,18 +14,121 @@ use std::fs::File;
 
 use ImageSize;
 use types::{FontSize, Scalar};
-use character::{Character, CharacterCache};
+use character::{Character, CharacterCache, FontMetrics};
+use types::Rectangle;
+use super::atlas::Atlas;
+
+/// Where the image of a glyph is stored.
+enum GlyphTexture<T> {
+    /// A texture of its own, with its id.
+    Texture(T, usize),
+    /// A rectangle in an atlas page.
+    Atlas {
+        page: usize,
+        offset: [u32; 2],
+        size: [u32; 2],
+    },
+}
+
+/// The font size in pixels, the character and the subpixel offset of a glyph.
+type Key = (FontSize, char, u32);
+
+/// A rendered glyph.
+struct Glyph<T> {
+    offset: [Scalar; 2],
+    size: [Scalar; 2],
+    texture: GlyphTexture<T>,
+    // The number of texels of the image.
+    texels: usize,
+    // When the glyph was last used.
+    last_used: u64,
+}
+
+/// The maximum amount of glyphs to keep in a cache
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capacity {
+    /// A number of glyphs.
+    Glyphs(usize),
+    /// A number of texels of the glyph images.
+    Texels(usize),
+}
+
+/// The settings of glyphs rendered as signed distance fields
+#[derive(Copy, Clone, Debug)]
+struct DistanceField {
+    /// The size in pixels all glyphs are rendered at.
+    size: u32,
+    /// The largest distance stored, in pixels at the reference size.
+    spread: u32,
+}
+
+/// Statistics of a glyph cache
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of glyphs found in the cache.
+    pub hits: u64,
+    /// The number of glyphs rendered.
+    pub misses: u64,
+    /// The number of glyphs removed to stay within capacity.
+    pub evictions: u64,
+    /// The number of glyphs in the cache.
+    pub glyphs: usize,
+    /// The number of texels of the glyph images in the cache.
+    pub texels: usize,
+}
 
 /// A struct used for caching rendered font.
+///
+/// By default, every glyph gets a texture of its own.
+/// Use `atlas` to pack glyphs into shared textures instead,
+/// so text can be drawn with fewer calls to the back-end.
+///
+/// Characters missing in the font are taken from the first `fallback` font
+/// that has them.
+/// The vertical metrics are those of the main font,
+/// so fallback glyphs can reach above the ascent or below the descent.
+///
+/// The cache grows without bound, unless a `capacity` is set.
+/// Then the least recently used glyphs are removed.
+///
+/// Use `distance_field` to render each glyph once as a signed distance field,
+/// which is scaled for every font size.
+///
+/// Use `subpixel` to render glyphs at several offsets within a pixel,
+/// for text at fractional positions.
+///
+/// Textures must implement `UpdateTexture` to draw characters,
+/// so glyphs can be added to the textures of atlas pages.
 pub struct GlyphCache<'a, F, T> {
     /// The font.
     pub font: rusttype::Font<'a>,
+    /// The fonts to use for characters missing in the font, in order.
+    pub fallbacks: Vec<rusttype::Font<'a>>,
     /// The factory used to create textures.
     pub factory: F,
     /// The settings to render the font with.
     settings: TextureSettings,
-    // Maps from fontsize and character to offset, size and texture.
-    data: HashMap<(FontSize, char), ([Scalar; 2], [Scalar; 2], T), BuildHasherDefault<FnvHasher>>,
+    // Maps from character to the font that has it, 0 being the main font.
+    fonts: HashMap<char, usize, BuildHasherDefault<FnvHasher>>,
+    // Maps from fontsize, character and subpixel offset to offset, size and texture.
+    data: HashMap<Key, Glyph<T>, BuildHasherDefault<FnvHasher>>,
+    // The packed glyph images, if glyphs are packed.
+    atlas: Option<Atlas>,
+    // The textures of the atlas pages, with their ids.
+    pages: Vec<(T, usize)>,
+    // The texels of atlas glyphs that were removed.
+    atlas_garbage: usize,
+    // The keys of glyphs by the time they were last used.
+    recency: BTreeMap<u64, Key>,
+    // The time of the last use.
+    tick: u64,
+    capacity: Option<Capacity>,
+    stats: CacheStats,
+    distance_field: Option<DistanceField>,
+    // The number of offsets within a pixel glyphs are rendered at.
+    subpixel: u32,
+    // The id of the last texture created.
+    texture_id: usize,
 }
 
 impl<'a, F, T> GlyphCache<'a, F, T>
@@ -38,7 +141,19 @@ impl<'a, F, T> GlyphCache<'a, F, T>
             font: font,
             factory: factory,
             settings: settings,
+            fallbacks: vec![],
+            fonts: HashMap::default(),
             data: HashMap::with_hasher(fnv),
+            atlas: None,
+            pages: vec![],
+            atlas_garbage: 0,
+            recency: BTreeMap::new(),
+            tick: 0,
+            capacity: None,
+            stats: CacheStats::default(),
+            distance_field: None,
+            subpixel: 1,
+            texture_id: 0,
         }
     }
 
@@ -49,19 +164,13 @@ impl<'a, F, T> GlyphCache<'a, F, T>
                   -> ::std::io::Result<GlyphCache<'static, F, T>>
         where P: AsRef<Path>
     {
-        let fnv = BuildHasherDefault::<FnvHasher>::default();
         let mut file = File::open(font)?;
         let mut file_buffer = Vec::new();
         file.read_to_end(&mut file_buffer)?;
 
         let collection = rusttype::FontCollection::from_bytes(file_buffer);
         let font = collection.into_font().unwrap();
-        Ok(GlyphCache {
-            font: font,
-            factory: factory,
-            settings: settings,
-            data: HashMap::with_hasher(fnv),
-        })
+        Ok(GlyphCache::from_font(font, factory, settings))
     }
 
     /// Creates a GlyphCache for a font stored in memory.
@@ -74,115 +183,547 @@ impl<'a, F, T> GlyphCache<'a, F, T>
         Ok(Self::from_font(font, factory, settings))
     }
 
-    /// Load all characters in the `chars` iterator for `size`
-    pub fn preload_chars<I>(&mut self, size: FontSize, chars: I) -> Result<(), T::Error>
+    /// Packs glyphs into shared textures.
+    ///
+    /// Pages start at `initial_size` pixels square,
+    /// and double in size up to `max_size` before a new page is added.
+    /// Adding a glyph updates the rectangle of the page it is in,
+    /// and the texture of a page is created again when the page grows.
+    pub fn atlas(mut self, initial_size: u32, max_size: u32) -> Self {
+        self.atlas = Some(Atlas::new(initial_size, max_size));
+        self
+    }
+
+    /// Renders glyphs as signed distance fields.
+    ///
+    /// Every glyph is rendered once at `reference_size`,
+    /// and scaled to the requested font size when drawn,
+    /// so text stays sharp when scaled or rotated by the transform.
+    /// Distances up to `spread` pixels at the reference size are stored,
+    /// which limits how far the glyphs can be scaled down.
+    ///
+    /// The cache is cleared.
+    /// Drawing requires a back-end that implements `Graphics::tri_list_uv_sdf`.
+    pub fn distance_field(mut self, reference_size: FontSize, spread: u32) -> Self {
+        self.distance_field = Some(DistanceField {
+            size: pixel_size(reference_size).max(1),
+            spread: spread.max(1),
+        });
+        self.clear();
+        self
+    }
+
+    /// Renders glyphs at several horizontal offsets within a pixel.
+    ///
+    /// A character at a pen position with a fraction of a pixel,
+    /// such as after characters with fractional advances,
+    /// is drawn with the glyph closest to the position, placed at a whole pixel.
+    /// This keeps the spacing of the font without blurring the glyphs.
+    /// Pen positions are in the coordinates text is drawn in, like `Text::round`.
+    /// Every glyph is rendered up to `steps` times, 1 by default.
+    /// Distance fields are drawn at any position and ignore this setting.
+    ///
+    /// The cache is cleared.
+    pub fn subpixel(mut self, steps: u32) -> Self {
+        self.subpixel = steps.max(1);
+        self.clear();
+        self
+    }
+
+    /// Returns the number of subpixel offsets glyphs are rendered at.
+    fn subpixel_steps(&self) -> u32 {
+        if self.distance_field.is_some() {
+            1
+        } else {
+            self.subpixel
+        }
+    }
+
+    /// Returns the size in pixels glyphs of a size are rendered at.
+    fn glyph_size(&self, size: u32) -> u32 {
+        self.distance_field.map(|field| field.size).unwrap_or(size)
+    }
+
+    /// Adds a font to use for characters missing in the previous fonts.
+    ///
+    /// `metrics` still uses the main font.
+    pub fn fallback(mut self, font: rusttype::Font<'a>) -> Self {
+        self.fallbacks.push(font);
+        self.fonts.clear();
+        self
+    }
+
+    /// Returns the index of the first font with a glyph for a character.
+    ///
+    /// The main font is 0, and the fallbacks follow.
+    /// Characters missing in all fonts use the main font.
+    fn font_index(&mut self, ch: char) -> usize {
+        use self::rusttype as rt;
+
+        if let Some(&i) = self.fonts.get(&ch) {
+            return i;
+        }
+        // Glyph zero is the replacement for missing glyphs.
+        let has_glyph = |font: &rusttype::Font| {
+            font.glyph(ch).map(|glyph| glyph.id() != rt::GlyphId(0)).unwrap_or(false)
+        };
+        let i = if has_glyph(&self.font) {
+            0
+        } else {
+            self.fallbacks.iter().position(has_glyph).map(|i| i + 1).unwrap_or(0)
+        };
+        self.fonts.insert(ch, i);
+        i
+    }
+
+    /// Returns a font by index, see `font_index`.
+    fn font_at(&self, i: usize) -> &rusttype::Font<'a> {
+        if i == 0 {
+            &self.font
+        } else {
+            &self.fallbacks[i - 1]
+        }
+    }
+
+    /// Sets the maximum amount of glyphs to keep.
+    ///
+    /// The capacity should fit the glyphs of the text drawn at once,
+    /// since glyphs drawn together are batched.
+    pub fn capacity(mut self, value: Capacity) -> Self {
+        self.capacity = Some(value);
+        self
+    }
+
+    /// Returns statistics of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Resets the hit, miss and eviction counts.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats {
+            glyphs: self.stats.glyphs,
+            texels: self.stats.texels,
+            ..CacheStats::default()
+        };
+    }
+
+    /// Removes all glyphs.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.recency.clear();
+        self.pages.clear();
+        if let Some(ref mut atlas) = self.atlas {
+            *atlas = Atlas::new(atlas.initial_size, atlas.max_size);
+        }
+        self.atlas_garbage = 0;
+        self.stats.glyphs = 0;
+        self.stats.texels = 0;
+    }
+
+    /// Removes the glyphs of a font size.
+    ///
+    /// The atlas is packed again when the next glyph is loaded,
+    /// so the remaining glyphs stay where they are until then.
+    pub fn evict_size(&mut self, size: FontSize) {
+        let size = self.glyph_size(pixel_size(size));
+        let keys: Vec<Key> = self.data.keys().filter(|key| key.0 == size).cloned().collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// Return `ch` for `size` if it's already cached. Don't load.
+    /// See the `preload_*` functions.
+    pub fn opt_character(&self, size: FontSize, ch: char) -> Option<Character<T>> {
+        self.variant_character(size, ch, 0)
+    }
+
+    /// Return `ch` for `size` at a subpixel offset if it's already cached.
+    fn variant_character<'b>(&'b self, size: FontSize, ch: char, variant: u32)
+                             -> Option<Character<'b, T>> {
+        let glyph = self.data.get(&(self.glyph_size(size), ch, variant))?;
+        let scale = size as Scalar / self.glyph_size(size) as Scalar;
+        let (texture, texture_id, atlas_offset, atlas_size) = match glyph.texture {
+            GlyphTexture::Texture(ref texture, id) => {
+                let (w, h) = texture.get_size();
+                (texture, id, [0.0; 2], [w as Scalar, h as Scalar])
+            }
+            GlyphTexture::Atlas { page, offset, size } => {
+                let (ref texture, id) = *self.pages.get(page)?;
+                (texture,
+                 id,
+                 [offset[0] as Scalar, offset[1] as Scalar],
+                 [size[0] as Scalar, size[1] as Scalar])
+            }
+        };
+        Some(Character {
+            offset: [glyph.offset[0] * scale, glyph.offset[1] * scale],
+            size: [glyph.size[0] * scale, glyph.size[1] * scale],
+            atlas_offset,
+            atlas_size,
+            image_size: [atlas_size[0] * scale, atlas_size[1] * scale],
+            texture,
+            texture_id: Some(texture_id),
+        })
+    }
+
+    /// Renders a glyph at a size in pixels and a subpixel offset, unless it is cached.
+    ///
+    /// Glyphs in the atlas are not uploaded.
+    fn load(&mut self, size: u32, ch: char, variant: u32) -> Result<(), T::Error> {
+        use self::rusttype as rt;
+
+        let key = (size, ch, variant);
+        if self.data.contains_key(&key) {
+            self.touch(key);
+            return Ok(());
+        }
+        self.stats.misses += 1;
+        // Glyphs removed since the last load make room first.
+        self.compact_atlas();
+
+        let font = self.font_index(ch);
+        let glyph = scaled_glyph(self.font_at(font), size, ch);
+        let h_metrics = glyph.h_metrics();
+        let bounding_box = glyph.exact_bounding_box().unwrap_or(rt::Rect {
+            min: rt::Point { x: 0.0, y: 0.0 },
+            max: rt::Point { x: 0.0, y: 0.0 },
+        });
+        let steps = self.subpixel_steps();
+        let glyph = glyph.positioned(rt::point(variant as f32 / steps as f32, 0.0));
+        let pixel_bounding_box = glyph.pixel_bounding_box().unwrap_or(rt::Rect {
+            min: rt::Point { x: 0, y: 0 },
+            max: rt::Point { x: 0, y: 0 },
+        });
+        // Distance fields need room for the distances outside the glyph.
+        let padding = self.distance_field.map(|field| field.spread + 1).unwrap_or(1);
+        let pixel_bb_width = pixel_bounding_box.width() + 2 * padding as i32;
+        let pixel_bb_height = pixel_bounding_box.height() + 2 * padding as i32;
+
+        let mut image_buffer = Vec::<u8>::new();
+        image_buffer.resize((pixel_bb_width * pixel_bb_height) as usize, 0);
+        glyph.draw(|x, y, v| {
+            let pos = ((x + padding) + (y + padding) * (pixel_bb_width as u32)) as usize;
+            image_buffer[pos] = (255.0 * v) as u8;
+        });
+        if let Some(field) = self.distance_field {
+            image_buffer = distance_field(&image_buffer,
+                                          pixel_bb_width as usize,
+                                          field.spread);
+        }
+
+        let texels = (pixel_bb_width * pixel_bb_height) as usize;
+        let texture = match self.atlas {
+            Some(ref mut atlas) => {
+                let size = [pixel_bb_width as u32, pixel_bb_height as u32];
+                let (page, offset) = atlas.insert(size[0], size[1], &image_buffer);
+                GlyphTexture::Atlas { page, offset, size }
+            }
+            None => {
+                let texture = if pixel_bb_width == 0 || pixel_bb_height == 0 {
+                    empty(&mut self.factory, &self.settings)?
+                } else {
+                    from_memory_alpha(&mut self.factory,
+                                      &image_buffer,
+                                      pixel_bb_width as u32,
+                                      pixel_bb_height as u32,
+                                      &self.settings)?
+                };
+                self.texture_id += 1;
+                GlyphTexture::Texture(texture, self.texture_id)
+            }
+        };
+        // With subpixel offsets, the image starts at a whole pixel from the origin,
+        // and `subpixel_character` moves it to the pen position.
+        let left = if steps > 1 {
+            pixel_bounding_box.min.x as Scalar
+        } else {
+            bounding_box.min.x as Scalar
+        };
+        self.data.insert(key, Glyph {
+            offset: [left - padding as Scalar,
+                     -pixel_bounding_box.min.y as Scalar + padding as Scalar],
+            size: [h_metrics.advance_width as Scalar, 0 as Scalar],
+            texture,
+            texels,
+            last_used: 0,
+        });
+        self.stats.glyphs += 1;
+        self.stats.texels += texels;
+        self.touch(key);
+        self.evict();
+        Ok(())
+    }
+
+    /// Marks a glyph as the most recently used.
+    fn touch(&mut self, key: Key) {
+        if let Some(glyph) = self.data.get_mut(&key) {
+            self.recency.remove(&glyph.last_used);
+            self.tick += 1;
+            glyph.last_used = self.tick;
+            self.recency.insert(self.tick, key);
+        }
+    }
+
+    /// Removes the least recently used glyphs until the cache is within capacity.
+    ///
+    /// The most recently used glyph is kept.
+    fn evict(&mut self) {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+        while self.recency.len() > 1 {
+            let full = match capacity {
+                Capacity::Glyphs(n) => self.stats.glyphs > n,
+                Capacity::Texels(n) => self.stats.texels > n,
+            };
+            if !full {
+                break;
+            }
+            let key = *self.recency.values().next().unwrap();
+            self.remove(key);
+            self.stats.evictions += 1;
+        }
+    }
+
+    /// Removes a glyph.
+    fn remove(&mut self, key: Key) {
+        if let Some(glyph) = self.data.remove(&key) {
+            self.recency.remove(&glyph.last_used);
+            self.stats.glyphs -= 1;
+            self.stats.texels -= glyph.texels;
+            if let GlyphTexture::Atlas { .. } = glyph.texture {
+                self.atlas_garbage += glyph.texels;
+            }
+        }
+    }
+
+    /// Packs the atlas again when more than half of it is removed glyphs.
+    ///
+    /// The pages must be uploaded again afterwards.
+    fn compact_atlas(&mut self) {
+        let atlas = match self.atlas {
+            Some(ref mut atlas) if 2 * self.atlas_garbage > atlas.area() => atlas,
+            _ => return,
+        };
+        let mut compact = Atlas::new(atlas.initial_size, atlas.max_size);
+        for glyph in self.data.values_mut() {
+            if let GlyphTexture::Atlas { ref mut page, ref mut offset, size } = glyph.texture {
+                let image = atlas.pages[*page].image(*offset, size[0], size[1]);
+                let (new_page, new_offset) = compact.insert(size[0], size[1], &image);
+                *page = new_page;
+                *offset = new_offset;
+            }
+        }
+        *atlas = compact;
+        self.pages.clear();
+        self.atlas_garbage = 0;
+    }
+}
+
+impl<'a, F, T> GlyphCache<'a, F, T>
+    where T: CreateTexture<F> + UpdateTexture<F, Error = <T as CreateTexture<F>>::Error> + ImageSize
+{
+    /// Load all characters in the `chars` iterator for `size`,
+    /// at every subpixel offset.
+    pub fn preload_chars<I>(&mut self, size: FontSize, chars: I) -> Result<(), <T as CreateTexture<F>>::Error>
         where I: Iterator<Item = char>
     {
+        let size = self.glyph_size(pixel_size(size));
         for ch in chars {
-            self.character(size, ch)?;
+            for variant in 0..self.subpixel_steps() {
+                self.load(size, ch, variant)?;
+            }
         }
-        Ok(())
+        self.upload()
     }
 
     /// Load all the printable ASCII characters for `size`. Includes space.
-    pub fn preload_printable_ascii(&mut self, size: FontSize) -> Result<(), T::Error> {
+    pub fn preload_printable_ascii(&mut self, size: FontSize) -> Result<(), <T as CreateTexture<F>>::Error> {
         // [0x20, 0x7F) contains all printable ASCII characters ([' ', '~'])
         self.preload_chars(size, (0x20u8..0x7F).map(|ch| ch as char))
     }
 
-    /// Return `ch` for `size` if it's already cached. Don't load.
-    /// See the `preload_*` functions.
-    pub fn opt_character(&self, size: FontSize, ch: char) -> Option<Character<T>> {
-        self.data.get(&(size, ch)).map(|&(offset, size, ref texture)| {
-            Character {
-                offset: offset,
-                size: size,
-                texture: texture,
+    /// Uploads the changed rectangles of the atlas pages.
+    ///
+    /// Textures are created for new pages and pages that grew.
+    fn upload(&mut self) -> Result<(), <T as CreateTexture<F>>::Error> {
+        if let Some(ref mut atlas) = self.atlas {
+            for (i, page) in atlas.pages.iter_mut().enumerate() {
+                let [x, y, w, h] = match page.dirty {
+                    Some(rect) => rect,
+                    None => continue,
+                };
+                let size = page.size;
+                let same_size = self.pages.get(i).map(|p| p.0.get_size() == (size, size));
+                if same_size == Some(true) {
+                    if w > 0 && h > 0 {
+                        let image = ops::alpha_to_rgba8(&page.image([x, y], w, h), [w, h]);
+                        self.pages[i].0.update(&mut self.factory, Format::Rgba8, &image, [x, y], [w, h])?;
+                    }
+                } else {
+                    let texture = from_memory_alpha(&mut self.factory,
+                                                    &page.data,
+                                                    size,
+                                                    size,
+                                                    &self.settings)?;
+                    self.texture_id += 1;
+                    let texture = (texture, self.texture_id);
+                    if i < self.pages.len() {
+                        self.pages[i] = texture;
+                    } else {
+                        self.pages.push(texture);
+                    }
+                }
+                page.dirty = None;
             }
-        })
+        }
+        Ok(())
     }
 }
 
 impl<'b, F, T: ImageSize> CharacterCache for GlyphCache<'b, F, T>
-    where T: CreateTexture<F>
+    where T: CreateTexture<F> + UpdateTexture<F, Error = <T as CreateTexture<F>>::Error>
 {
     type Texture = T;
-    type Error = T::Error;
+    type Error = <T as CreateTexture<F>>::Error;
 
     fn character<'a>(&'a mut self,
                      size: FontSize,
                      ch: char)
                      -> Result<Character<'a, T>, Self::Error> {
-        use std::collections::hash_map::Entry;
-        use self::rusttype as rt;
+        let size = pixel_size(size);
+        let glyph_size = self.glyph_size(size);
+        if self.data.contains_key(&(glyph_size, ch, 0)) {
+            self.stats.hits += 1;
+        }
+        self.load(glyph_size, ch, 0)?;
+        self.upload()?;
+        Ok(self.opt_character(size, ch).expect("glyph was loaded"))
+    }
 
-        let size = ((size as f32) * 1.333).round() as u32; // convert points to pixels
-
-        match self.data.entry((size, ch)) {
-            //returning `into_mut()' to get reference with 'a lifetime
-            Entry::Occupied(v) => {
-                let &mut (offset, size, ref texture) = v.into_mut();
-                Ok(Character {
-                    offset: offset,
-                    size: size,
-                    texture: texture,
-                })
-            }
-            Entry::Vacant(v) => {
-                // this is only None for invalid GlyphIds,
-                // but char is converted to a Codepoint which must result in a glyph.
-                let glyph = self.font.glyph(ch).unwrap();
-                let scale = rt::Scale::uniform(size as f32);
-                let mut glyph = glyph.scaled(scale);
-
-                // some fonts do not contain glyph zero as fallback, instead try U+FFFD.
-                if glyph.id() == rt::GlyphId(0) && glyph.shape().is_none() {
-                    glyph = self.font.glyph('\u{FFFD}').unwrap().scaled(scale);
-                }
+    fn subpixel_character<'a>(&'a mut self,
+                              size: FontSize,
+                              ch: char,
+                              x: Scalar)
+                              -> Result<Character<'a, T>, Self::Error> {
+        let steps = self.subpixel_steps();
+        if steps == 1 {
+            return self.character(size, ch);
+        }
+        let (variant, shift) = subpixel_variant(x, steps);
+        let size = pixel_size(size);
+        if self.data.contains_key(&(size, ch, variant)) {
+            self.stats.hits += 1;
+        }
+        self.load(size, ch, variant)?;
+        self.upload()?;
+        let mut character = self.variant_character(size, ch, variant).expect("glyph was loaded");
+        character.offset[0] += shift;
+        Ok(character)
+    }
+
+    fn is_distance_field(&self) -> bool {
+        self.distance_field.is_some()
+    }
 
-                let h_metrics = glyph.h_metrics();
-                let bounding_box = glyph.exact_bounding_box().unwrap_or(rt::Rect {
-                    min: rt::Point { x: 0.0, y: 0.0 },
-                    max: rt::Point { x: 0.0, y: 0.0 },
-                });
-                let glyph = glyph.positioned(rt::point(0.0, 0.0));
-                let pixel_bounding_box = glyph.pixel_bounding_box().unwrap_or(rt::Rect {
-                    min: rt::Point { x: 0, y: 0 },
-                    max: rt::Point { x: 0, y: 0 },
-                });
-                let pixel_bb_width = pixel_bounding_box.width() + 2;
-                let pixel_bb_height = pixel_bounding_box.height() + 2;
-
-                let mut image_buffer = Vec::<u8>::new();
-                image_buffer.resize((pixel_bb_width * pixel_bb_height) as usize, 0);
-                glyph.draw(|x, y, v| {
-                    let pos = ((x + 1) + (y + 1) * (pixel_bb_width as u32)) as usize;
-                    image_buffer[pos] = (255.0 * v) as u8;
-                });
-
-                let &mut (offset, size, ref texture) =
-                    v.insert(([bounding_box.min.x as Scalar - 1.0,
-                               -pixel_bounding_box.min.y as Scalar + 1.0],
-                              [h_metrics.advance_width as Scalar, 0 as Scalar],
-                              {
-                                  if pixel_bb_width == 0 || pixel_bb_height == 0 {
-                                      empty(&mut self.factory, &self.settings)?
-                                  } else {
-                                      from_memory_alpha(&mut self.factory,
-                                                        &image_buffer,
-                                                        pixel_bb_width as u32,
-                                                        pixel_bb_height as u32,
-                                                        &self.settings)?
-                                  }
-                              }));
-                Ok(Character {
-                    offset: offset,
-                    size: size,
-                    texture: texture,
-                })
+    fn distance_field_spread(&self) -> Scalar {
+        self.distance_field.map(|field| field.spread as Scalar).unwrap_or(0.0)
+    }
+
+    fn kerning(&mut self, size: FontSize, first: char, second: char) -> Result<Scalar, Self::Error> {
+        let scale = rusttype::Scale::uniform(pixel_size(size) as f32);
+        let font = self.font_index(first);
+        if font != self.font_index(second) {
+            return Ok(0.0);
+        }
+        Ok(self.font_at(font).pair_kerning(scale, first, second) as Scalar)
+    }
+
+    fn ink_bounds(&mut self, size: FontSize, ch: char) -> Result<Option<Rectangle>, Self::Error> {
+        let font = self.font_index(ch);
+        let glyph = scaled_glyph(self.font_at(font), pixel_size(size), ch);
+        Ok(glyph.exact_bounding_box().map(|bb| {
+            [bb.min.x as Scalar,
+             bb.min.y as Scalar,
+             (bb.max.x - bb.min.x) as Scalar,
+             (bb.max.y - bb.min.y) as Scalar]
+        }))
+    }
+
+    fn metrics(&mut self, size: FontSize) -> Result<FontMetrics, Self::Error> {
+        let v_metrics = self.font.v_metrics(rusttype::Scale::uniform(pixel_size(size) as f32));
+        Ok(FontMetrics {
+            ascent: v_metrics.ascent as Scalar,
+            descent: v_metrics.descent as Scalar,
+            line_gap: v_metrics.line_gap as Scalar,
+        })
+    }
+}
+
+/// Converts points to pixels.
+fn pixel_size(size: FontSize) -> u32 {
+    ((size as f32) * 1.333).round() as u32
+}
+
+fn scaled_glyph<'a>(font: &rusttype::Font<'a>, size: u32, ch: char) -> rusttype::ScaledGlyph<'a> {
+    use self::rusttype as rt;
+
+    // this is only None for invalid GlyphIds,
+    // but char is converted to a Codepoint which must result in a glyph.
+    let scale = rt::Scale::uniform(size as f32);
+    let glyph = font.glyph(ch).unwrap().scaled(scale);
+
+    // some fonts do not contain glyph zero as fallback, instead try U+FFFD.
+    if glyph.id() == rt::GlyphId(0) && glyph.shape().is_none() {
+        font.glyph('\u{FFFD}').unwrap().scaled(scale)
+    } else {
+        glyph
+    }
+}
+
+/// Converts the coverage of a glyph image to a signed distance field.
+///
+/// The distance to the outline is estimated from the nearest pixels on the other side,
+/// using the coverage of pixels on the outline for distances within a pixel.
+/// Distances are stored as 0.5 on the outline, 1 at `spread` pixels inside
+/// and 0 at `spread` pixels outside.
+fn distance_field(coverage: &[u8], width: usize, spread: u32) -> Vec<u8> {
+    if width == 0 {
+        return vec![];
+    }
+    let height = coverage.len() / width;
+    // Approximates the distance from the pixel center to the outline, within a pixel.
+    let edge = |i: usize| coverage[i] as f32 / 255.0 - 0.5;
+    let radius = spread as isize + 1;
+    let mut field = Vec::with_capacity(coverage.len());
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let e = edge(y as usize * width + x as usize);
+            let inside = e >= 0.0;
+            let mut distance = if e.abs() < 0.5 { e.abs() } else { spread as f32 };
+            for qy in (y - radius).max(0)..(y + radius + 1).min(height as isize) {
+                for qx in (x - radius).max(0)..(x + radius + 1).min(width as isize) {
+                    let eq = edge(qy as usize * width + qx as usize);
+                    if (eq >= 0.0) == inside {
+                        continue;
+                    }
+                    let (dx, dy) = ((qx - x) as f32, (qy - y) as f32);
+                    let d = ((dx * dx + dy * dy).sqrt() - eq.abs()).max(0.0);
+                    distance = distance.min(d);
+                }
             }
+            let distance = if inside { distance } else { -distance };
+            let value = 0.5 + distance / (2.0 * spread as f32);
+            // `clamp` needs Rust 1.50.
+            #[allow(clippy::manual_clamp)]
+            let value = (255.0 * value).round().max(0.0).min(255.0) as u8;
+            field.push(value);
         }
     }
+    field
 }
 
 fn empty<F, T: CreateTexture<F>>(factory: &mut F,
@@ -201,3 +742,123 @@ fn from_memory_alpha<F, T: CreateTexture<F>>(factory: &mut F,
     let buffer: Vec<u8> = ops::alpha_to_rgba8(buf, size);
     CreateTexture::create(factory, Format::Rgba8, &buffer, size, settings)
 }
+
+/// Returns the subpixel offset closest to the fraction of `x`,
+/// and the distance from the whole pixel below `x` to the pixel it starts at.
+fn subpixel_variant(x: Scalar, steps: u32) -> (u32, Scalar) {
+    let fraction = x - x.floor();
+    let step = (fraction * steps as Scalar).round() as u32;
+    // Fractions close to the next pixel use the glyph at the next pixel.
+    if step == steps {
+        (0, 1.0 - fraction)
+    } else {
+        (step, -fraction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use software;
+
+    /// Returns a TrueType font where every character is an empty glyph.
+    fn empty_font() -> Vec<u8> {
+        fn be(data: &mut Vec<u8>, value: u32, bytes: usize) {
+            for i in (0..bytes).rev() {
+                data.push((value >> (8 * i)) as u8);
+            }
+        }
+        // A byte encoding mapping every character to glyph 0.
+        let mut cmap = vec![];
+        for &v in &[0, 1, 0, 3] {
+            be(&mut cmap, v, 2);
+        }
+        be(&mut cmap, 12, 4);
+        for &v in &[0, 262, 0] {
+            be(&mut cmap, v, 2);
+        }
+        cmap.extend_from_slice(&[0; 256]);
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&[0x03, 0xE8]);
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&[0x03, 0x20]);
+        hhea[6..8].copy_from_slice(&[0xFF, 0x38]);
+        hhea[35] = 1;
+        let hmtx = vec![0x01, 0xF4, 0, 0];
+        let loca = vec![0; 4];
+        let glyf = vec![0; 4];
+        let tables: [(&[u8], &Vec<u8>); 6] = [(b"cmap", &cmap), (b"glyf", &glyf), (b"head", &head),
+                                              (b"hhea", &hhea), (b"hmtx", &hmtx), (b"loca", &loca)];
+        let mut font = vec![];
+        be(&mut font, 0x10000, 4);
+        for &v in &[tables.len() as u32, 0, 0, 0] {
+            be(&mut font, v, 2);
+        }
+        let mut offset = 12 + 16 * tables.len();
+        for &(tag, data) in &tables {
+            font.extend_from_slice(tag);
+            be(&mut font, 0, 4);
+            be(&mut font, offset as u32, 4);
+            be(&mut font, data.len() as u32, 4);
+            offset += data.len();
+        }
+        for &(_, data) in &tables {
+            font.extend_from_slice(data);
+        }
+        font
+    }
+
+    #[test]
+    fn test_evict_size() {
+        let data = empty_font();
+        let font = rusttype::FontCollection::from_bytes(&data[..]).into_font().unwrap();
+        // Glyphs of 2x2 pixels with the padding, four to a page.
+        let mut cache: GlyphCache<(), software::Texture> =
+            GlyphCache::from_font(font, (), TextureSettings::new()).atlas(4, 4);
+        for ch in "abc".chars() {
+            cache.character(10, ch).unwrap();
+        }
+        cache.character(20, 'd').unwrap();
+        cache.evict_size(10);
+        // Cached glyphs are looked up by the size in pixels.
+        assert!(cache.opt_character(pixel_size(10), 'a').is_none());
+        assert!(cache.opt_character(pixel_size(20), 'd').is_some());
+        // The atlas is packed when the next glyph is loaded.
+        cache.character(20, 'e').unwrap();
+        assert!(cache.opt_character(pixel_size(20), 'd').is_some());
+        assert_eq!(cache.atlas.as_ref().unwrap().area(), 16);
+        assert_eq!(cache.stats().glyphs, 2);
+    }
+
+    #[test]
+    fn test_subpixel_variant() {
+        assert_eq!(subpixel_variant(10.0, 4), (0, 0.0));
+        assert_eq!(subpixel_variant(10.25, 4), (1, -0.25));
+        assert_eq!(subpixel_variant(10.3, 4).0, 1);
+        assert_eq!(subpixel_variant(-0.5, 4), (2, -0.5));
+        // Close to the next pixel, the image starts there.
+        assert_eq!(subpixel_variant(10.875, 4), (0, 0.125));
+        assert_eq!(subpixel_variant(10.5, 1), (0, 0.5));
+    }
+
+    #[test]
+    fn test_distance_field() {
+        // A 4x4 square in the middle of a 12x12 image.
+        let coverage: Vec<u8> = (0..144)
+            .map(|i| if i % 12 >= 4 && i % 12 < 8 && i / 12 >= 4 && i / 12 < 8 { 255 } else { 0 })
+            .collect();
+        let field = distance_field(&coverage, 12, 4);
+        let at = |x: usize, y: usize| field[y * 12 + x];
+        // Pixels next to the outline are half a pixel from it,
+        // which is 0.5 +- 0.5 / (2 * 4).
+        assert_eq!(at(4, 5), 143);
+        assert_eq!(at(3, 5), 112);
+        assert!(at(5, 5) > at(4, 5));
+        assert!(at(2, 5) < at(3, 5));
+        // Distances beyond the spread are clamped.
+        assert_eq!(at(0, 0), 0);
+        // The field is symmetric.
+        assert_eq!(at(3, 5), at(8, 5));
+        assert_eq!(at(5, 3), at(5, 8));
+    }
+}
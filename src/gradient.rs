@@ -0,0 +1,402 @@
+//! Linear and radial gradients
+//!
+//! A gradient paints shapes with colors that change smoothly between
+//! color stops. The gradient is specified in the local coordinates of
+//! the shape, before the transform.
+//!
+//! ```
+//! use graphics::gradient::Gradient;
+//!
+//! let gradient = Gradient::linear([0.0, 0.0], [100.0, 0.0])
+//!     .stop(0.0, [1.0, 0.0, 0.0, 1.0])
+//!     .stop(1.0, [0.0, 0.0, 1.0, 1.0]);
+//! assert_eq!(gradient.color_at([50.0, 20.0]), [0.5, 0.0, 0.5, 1.0]);
+//! ```
+//!
+//! Shapes are drawn with gradients through `Graphics::tri_list_c`.
+
+use types::{Color, Radius};
+use math::{Matrix2d, Scalar, Vec2d};
+use triangulation::{tx, ty};
+use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+/// The number of steps per radius used to split triangles for radial gradients.
+const RADIAL_RESOLUTION: Scalar = 32.0;
+
+/// The maximum number of times a triangle is split in half.
+const MAX_DEPTH: usize = 16;
+
+/// The geometry of a gradient
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Colors change along a line, from offset 0 at the start to 1 at the end.
+    Linear {
+        /// The start point.
+        start: Vec2d,
+        /// The end point.
+        end: Vec2d,
+    },
+    /// Colors change with the distance to a center,
+    /// from offset 0 at the center to 1 at the radius.
+    Radial {
+        /// The center.
+        center: Vec2d,
+        /// The radius.
+        radius: Radius,
+    },
+}
+
+/// A color at an offset of a gradient
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stop {
+    /// The offset, usually from 0 to 1.
+    pub offset: f32,
+    /// The color at the offset.
+    pub color: Color,
+}
+
+/// A gradient with color stops
+///
+/// Colors are interpolated between stops.
+/// Before the first stop and after the last stop, the color is constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// The geometry of the gradient.
+    pub shape: Shape,
+    /// The color stops, sorted by offset.
+    pub stops: Vec<Stop>,
+}
+
+impl Gradient {
+    /// Creates a new linear gradient without stops.
+    pub fn linear(start: Vec2d, end: Vec2d) -> Gradient {
+        Gradient {
+            shape: Shape::Linear { start, end },
+            stops: vec![],
+        }
+    }
+
+    /// Creates a new radial gradient without stops.
+    pub fn radial(center: Vec2d, radius: Radius) -> Gradient {
+        Gradient {
+            shape: Shape::Radial { center, radius },
+            stops: vec![],
+        }
+    }
+
+    /// Adds a color stop.
+    ///
+    /// Stops with the same offset keep the order they were added in,
+    /// which gives a sharp change of color.
+    pub fn stop(mut self, offset: f32, color: Color) -> Self {
+        let i = self.stops.iter().take_while(|s| s.offset <= offset).count();
+        self.stops.insert(i, Stop { offset, color });
+        self
+    }
+
+    /// Computes the gradient offset at a point.
+    pub fn offset_at(&self, p: Vec2d) -> Scalar {
+        match self.shape {
+            Shape::Linear { start, end } => {
+                let d = [end[0] - start[0], end[1] - start[1]];
+                let len2 = d[0] * d[0] + d[1] * d[1];
+                if len2 == 0.0 {
+                    return 1.0;
+                }
+                ((p[0] - start[0]) * d[0] + (p[1] - start[1]) * d[1]) / len2
+            }
+            Shape::Radial { center, radius } => {
+                let d = [p[0] - center[0], p[1] - center[1]];
+                let dist = (d[0] * d[0] + d[1] * d[1]).sqrt();
+                if radius == 0.0 { 1.0 } else { dist / radius }
+            }
+        }
+    }
+
+    /// Computes the color at a gradient offset.
+    ///
+    /// Returns a transparent color when there are no stops.
+    pub fn color_at_offset(&self, offset: Scalar) -> Color {
+        let offset = offset as f32;
+        let stops = &self.stops;
+        if stops.is_empty() {
+            return [0.0; 4];
+        }
+        if offset < stops[0].offset {
+            return stops[0].color;
+        }
+        for w in stops.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            if offset < b.offset {
+                let t = (offset - a.offset) / (b.offset - a.offset);
+                let mut res = [0.0; 4];
+                for (i, r) in res.iter_mut().enumerate() {
+                    *r = a.color[i] + (b.color[i] - a.color[i]) * t;
+                }
+                return res;
+            }
+        }
+        stops[stops.len() - 1].color
+    }
+
+    /// Computes the color at a point.
+    pub fn color_at(&self, p: Vec2d) -> Color {
+        self.color_at_offset(self.offset_at(p))
+    }
+
+    /// Streams triangles with vertex colors from the gradient.
+    ///
+    /// The triangles are in the local coordinates of the gradient,
+    /// and are transformed with `m` when streamed.
+    /// Triangles are split such that the colors are interpolated
+    /// correctly between vertices.
+    /// Linear gradients are split exactly at the color stops,
+    /// while radial gradients are split into small triangles.
+    ///
+    /// The chunks are suitable for `Graphics::tri_list_c`.
+    pub fn stream_tri_list_c<F>(&self, m: Matrix2d, triangles: &[[f32; 2]], mut f: F)
+        where F: FnMut(&[[f32; 2]], &[[f32; 4]])
+    {
+        let mut vertices: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        let mut colors: [[f32; 4]; BUFFER_SIZE] = [[0.0; 4]; BUFFER_SIZE];
+        let max = BUFFER_SIZE - BUFFER_SIZE % 3;
+        let mut i = 0;
+        {
+            let mut push = |tri: [Vec2d; 3], color: [Color; 3]| {
+                for k in 0..3 {
+                    let p = tri[k];
+                    vertices[i] = [tx(m, p[0], p[1]), ty(m, p[0], p[1])];
+                    colors[i] = color[k];
+                    i += 1;
+                }
+                if i == max {
+                    f(&vertices[..i], &colors[..i]);
+                    i = 0;
+                }
+            };
+            for t in triangles.chunks(3).filter(|t| t.len() == 3) {
+                let tri = [[t[0][0] as Scalar, t[0][1] as Scalar],
+                           [t[1][0] as Scalar, t[1][1] as Scalar],
+                           [t[2][0] as Scalar, t[2][1] as Scalar]];
+                match self.shape {
+                    Shape::Linear { .. } => self.split_linear(tri, &mut push),
+                    Shape::Radial { center, radius } => {
+                        self.split_radial(tri, center, radius, &mut push)
+                    }
+                }
+            }
+        }
+        if i > 0 {
+            f(&vertices[..i], &colors[..i]);
+        }
+    }
+
+    /// Splits a triangle along the lines of the color stops.
+    // The associated float constants need Rust 1.43.
+    #[allow(clippy::legacy_numeric_constants)]
+    fn split_linear<F>(&self, tri: [Vec2d; 3], push: &mut F)
+        where F: FnMut([Vec2d; 3], [Color; 3])
+    {
+        let stops = &self.stops;
+        if stops.is_empty() {
+            return;
+        }
+        // Bands of offsets with colors at their ends.
+        let first = stops[0];
+        let last = stops[stops.len() - 1];
+        let mut bands = vec![(::std::f64::NEG_INFINITY, first.offset as Scalar, first.color, first.color)];
+        for w in stops.windows(2).filter(|w| w[0].offset < w[1].offset) {
+            bands.push((w[0].offset as Scalar, w[1].offset as Scalar, w[0].color, w[1].color));
+        }
+        bands.push((last.offset as Scalar, ::std::f64::INFINITY, last.color, last.color));
+
+        let polygon: Vec<(Vec2d, Scalar)> = tri.iter().map(|&p| (p, self.offset_at(p))).collect();
+        for &(lo, hi, a, b) in &bands {
+            let polygon = clip(&polygon, |t| t - lo);
+            let polygon = clip(&polygon, |t| hi - t);
+            let color = |t: Scalar| -> Color {
+                let t = if lo < hi && lo.is_finite() && hi.is_finite() {
+                    ((t - lo) / (hi - lo)) as f32
+                } else {
+                    0.0
+                };
+                [a[0] + (b[0] - a[0]) * t,
+                 a[1] + (b[1] - a[1]) * t,
+                 a[2] + (b[2] - a[2]) * t,
+                 a[3] + (b[3] - a[3]) * t]
+            };
+            for k in 2..polygon.len() {
+                let (p, q, r) = (polygon[0], polygon[k - 1], polygon[k]);
+                push([p.0, q.0, r.0], [color(p.1), color(q.1), color(r.1)]);
+            }
+        }
+    }
+
+    /// Returns whether a triangle has a single color of a radial gradient,
+    /// because it is within the first stop or outside the last stop.
+    fn is_single_color(&self, t: [Vec2d; 3], center: Vec2d, radius: Radius) -> bool {
+        let stops = &self.stops;
+        if radius <= 0.0 || stops.is_empty() {
+            return true;
+        }
+        let first = stops[0].offset as Scalar * radius;
+        let last = stops[stops.len() - 1].offset as Scalar * radius;
+        let dist = |p: Vec2d| ((p[0] - center[0]).powi(2) + (p[1] - center[1]).powi(2)).sqrt();
+        t.iter().all(|&p| dist(p) <= first) || distance_to_triangle(center, t) >= last
+    }
+
+    /// Splits a triangle into small triangles.
+    ///
+    /// Sides longer than the step are split in half,
+    /// which only depends on the side, such that triangles sharing
+    /// a side are split at the same points without cracks.
+    /// Triangles with a single color, before the first stop
+    /// or after the last stop, are not split.
+    fn split_radial<F>(&self, tri: [Vec2d; 3], center: Vec2d, radius: Radius, push: &mut F)
+        where F: FnMut([Vec2d; 3], [Color; 3])
+    {
+        let step = radius / RADIAL_RESOLUTION;
+        let mut stack = vec![(tri, 0)];
+        while let Some((t, depth)) = stack.pop() {
+            let len2 = |a: Vec2d, b: Vec2d| (b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2);
+            let sides = [len2(t[0], t[1]), len2(t[1], t[2]), len2(t[2], t[0])];
+            let mut k = 0;
+            for i in 1..3 {
+                if sides[i] > sides[k] {
+                    k = i;
+                }
+            }
+            if depth >= MAX_DEPTH || sides[k] <= step * step ||
+               self.is_single_color(t, center, radius) {
+                push(t, [self.color_at(t[0]), self.color_at(t[1]), self.color_at(t[2])]);
+                continue;
+            }
+            let (a, b, c) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+            let mid = [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5];
+            stack.push(([a, mid, c], depth + 1));
+            stack.push(([mid, b, c], depth + 1));
+        }
+    }
+}
+
+/// Returns the distance from a point to a triangle, which is zero inside.
+fn distance_to_triangle(p: Vec2d, t: [Vec2d; 3]) -> Scalar {
+    let cross = |a: Vec2d, b: Vec2d| (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+    let sides = [cross(t[0], t[1]), cross(t[1], t[2]), cross(t[2], t[0])];
+    if sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0) {
+        return 0.0;
+    }
+    let distance_to_side = |a: Vec2d, b: Vec2d| {
+        let d = [b[0] - a[0], b[1] - a[1]];
+        let len2 = d[0] * d[0] + d[1] * d[1];
+        // `clamp` needs Rust 1.50.
+        #[allow(clippy::manual_clamp)]
+        let s = if len2 > 0.0 {
+            (((p[0] - a[0]) * d[0] + (p[1] - a[1]) * d[1]) / len2).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+        let q = [a[0] + d[0] * s - p[0], a[1] + d[1] * s - p[1]];
+        (q[0] * q[0] + q[1] * q[1]).sqrt()
+    };
+    distance_to_side(t[0], t[1])
+        .min(distance_to_side(t[1], t[2]))
+        .min(distance_to_side(t[2], t[0]))
+}
+
+/// Clips a convex polygon with gradient offsets,
+/// keeping the part where `inside` is not negative.
+fn clip<F>(polygon: &[(Vec2d, Scalar)], inside: F) -> Vec<(Vec2d, Scalar)>
+    where F: Fn(Scalar) -> Scalar
+{
+    let mut res = vec![];
+    let n = polygon.len();
+    for i in 0..n {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        let (da, db) = (inside(a.1), inside(b.1));
+        if da >= 0.0 {
+            res.push(a);
+        }
+        if (da < 0.0) != (db < 0.0) {
+            let t = da / (da - db);
+            res.push(([a.0[0] + (b.0[0] - a.0[0]) * t, a.0[1] + (b.0[1] - a.0[1]) * t],
+                      a.1 + (b.1 - a.1) * t));
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::identity;
+
+    #[test]
+    fn test_color_at() {
+        let gradient = Gradient::radial([0.0, 0.0], 10.0)
+            .stop(1.0, [0.0, 0.0, 0.0, 1.0])
+            .stop(0.5, [1.0; 4]);
+        assert_eq!(gradient.stops[0].offset, 0.5);
+        assert_eq!(gradient.color_at([0.0, 2.0]), [1.0; 4]);
+        assert_eq!(gradient.color_at([0.0, -7.5]), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(gradient.color_at([20.0, 0.0]), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(Gradient::linear([0.0; 2], [1.0, 0.0]).color_at([0.0; 2]), [0.0; 4]);
+    }
+
+    #[test]
+    fn test_sharp_stop() {
+        let gradient = Gradient::linear([0.0, 0.0], [2.0, 0.0])
+            .stop(0.5, [1.0; 4])
+            .stop(0.5, [0.0; 4]);
+        gradient.stream_tri_list_c(identity(), &[[0.0, 0.0], [2.0, 0.0], [0.0, 1.0]], |v, c| {
+            for (v, c) in v.iter().zip(c) {
+                // Every triangle is on one side of the sharp stop.
+                assert!(v[0] <= 1.0 || c[0] == 0.0);
+                assert!(v[0] >= 1.0 || c[0] == 1.0);
+            }
+        });
+    }
+
+    #[test]
+    fn test_split_linear() {
+        let gradient = Gradient::linear([0.0, 0.0], [4.0, 0.0])
+            .stop(0.25, [1.0; 4])
+            .stop(0.75, [0.0; 4]);
+        let triangle = [[0.0, 0.0], [4.0, 0.0], [0.0, 4.0]];
+        let mut area = 0.0;
+        gradient.stream_tri_list_c(identity(), &triangle, |vertices, colors| {
+            assert_eq!(vertices.len(), colors.len());
+            for (v, c) in vertices.chunks(3).zip(colors.chunks(3)) {
+                area += ((v[1][0] - v[0][0]) * (v[2][1] - v[0][1]) -
+                         (v[2][0] - v[0][0]) * (v[1][1] - v[0][1])).abs() / 2.0;
+                // The colors are exact at every vertex.
+                for (p, c) in v.iter().zip(c) {
+                    let expected = gradient.color_at([p[0] as Scalar, p[1] as Scalar]);
+                    assert!((expected[0] - c[0]).abs() < 1e-6);
+                }
+            }
+        });
+        assert!((area - 8.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_split_radial() {
+        let count = |gradient: &Gradient, triangle: &[[f32; 2]]| {
+            let mut n = 0;
+            gradient.stream_tri_list_c(identity(), triangle, |vertices, _| n += vertices.len() / 3);
+            n
+        };
+        let gradient = |radius| {
+            Gradient::radial([0.0, 0.0], radius)
+                .stop(0.5, [1.0; 4])
+                .stop(1.0, [0.0, 0.0, 0.0, 1.0])
+        };
+        // Triangles with a single color are not split.
+        assert_eq!(count(&gradient(0.0), &[[0.0, 0.0], [1000.0, 0.0], [0.0, 1000.0]]), 1);
+        assert_eq!(count(&gradient(10.0), &[[20.0, 0.0], [1000.0, 0.0], [20.0, 1000.0]]), 1);
+        assert_eq!(count(&gradient(10.0), &[[1.0, 0.0], [-1.0, 0.0], [0.0, 1.0]]), 1);
+        // A triangle around the gradient is split, although its corners have the same color.
+        assert!(count(&gradient(10.0), &[[-100.0, -100.0], [100.0, -100.0], [0.0, 100.0]]) > 1);
+        assert_eq!(distance_to_triangle([0.0, 3.0], [[-1.0, 0.0], [1.0, 0.0], [0.0, 1.0]]), 2.0);
+    }
+}
@@ -1,7 +1,8 @@
 use DrawState;
+use triangulation;
 use types::{self, Matrix2d, Scalar};
 use deform::DeformGrid;
-use {CircleArc, Ellipse, Image, ImageSize, Line, Polygon, Rectangle};
+use {CircleArc, ComplexPolygon, Ellipse, Image, ImageSize, Line, Polygon, Polyline, Rectangle};
 
 /// Implemented by all graphics back-ends.
 ///
@@ -112,6 +113,232 @@ pub trait Graphics: Sized {
                       f: F)
         where F: FnMut(&mut FnMut(&[[f32; 2]], &[[f32; 2]]));
 
+    /// Renders list of 2d triangles using a color and a signed distance field texture.
+    ///
+    /// The alpha of the texture is 0.5 on the outline of the shape,
+    /// increasing inside and decreasing outside.
+    /// Back-ends should draw where the alpha is above `threshold`,
+    /// smoothing the edge over about a pixel on the screen,
+    /// so the shape stays sharp when scaled or rotated.
+    /// A threshold of 0.5 draws the shape, and lower thresholds grow it.
+    ///
+    /// Works like `tri_list_uv`.
+    ///
+    /// The default implementation renders the distance field with `tri_list_uv`,
+    /// which blurs the edges and ignores the threshold.
+    /// Back-ends should override this method for better quality.
+    ///
+    /// Color space is sRGB.
+    fn tri_list_uv_sdf<F>(&mut self,
+                          draw_state: &DrawState,
+                          color: &[f32; 4],
+                          texture: &<Self as Graphics>::Texture,
+                          _threshold: f32,
+                          f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        self.tri_list_uv(draw_state, color, texture, f);
+    }
+
+    /// Renders list of 2d triangles using a solid color and an index buffer.
+    ///
+    /// All vertices share the same color.
+    ///
+    /// The back-end calls the closure with a closure to receive vertices and indices.
+    /// First, the back-end sets up shaders and such to prepare.
+    /// Then it calls the closure, which calls back with chunks of vertices and indices.
+    /// The number of vertices and the number of indices per chunk never exceed
+    /// `BACK_END_MAX_VERTEX_COUNT`.
+    /// Vertex positions are encoded `[[x0, y0], [x1, y1], ...]`.
+    /// Indices are encoded `[a0, b0, c0, a1, b1, c1, ...]`, three per triangle,
+    /// and refer to the vertices of the same chunk.
+    ///
+    /// The default implementation expands the indices and renders with `tri_list`.
+    ///
+    /// Color space is sRGB.
+    fn tri_list_indexed<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[u16]))
+    {
+        use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+        let mut buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        self.tri_list(draw_state, color, |g| {
+            f(&mut |vertices: &[[f32; 2]], indices: &[u16]| {
+                for chunk in indices.chunks(BUFFER_SIZE - BUFFER_SIZE % 3) {
+                    for (out, &i) in buffer.iter_mut().zip(chunk) {
+                        *out = vertices[i as usize];
+                    }
+                    g(&buffer[..chunk.len()]);
+                }
+            })
+        });
+    }
+
+    /// Renders list of 2d triangles using a color, a texture and an index buffer.
+    ///
+    /// All vertices share the same color.
+    ///
+    /// Works like `tri_list_indexed`, with a texture coordinate per vertex.
+    /// Arguments are `|vertices: &[f32], texture_coords: &[f32], indices: &[u16]`.
+    ///
+    /// The default implementation expands the indices and renders with `tri_list_uv`.
+    ///
+    /// Color space is sRGB.
+    fn tri_list_uv_indexed<F>(&mut self,
+                              draw_state: &DrawState,
+                              color: &[f32; 4],
+                              texture: &<Self as Graphics>::Texture,
+                              mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[u16]))
+    {
+        use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+        let mut buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        let mut uv_buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        self.tri_list_uv(draw_state, color, texture, |g| {
+            f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]], indices: &[u16]| {
+                for chunk in indices.chunks(BUFFER_SIZE - BUFFER_SIZE % 3) {
+                    for ((out, uv), &i) in buffer.iter_mut().zip(uv_buffer.iter_mut()).zip(chunk) {
+                        *out = vertices[i as usize];
+                        *uv = texture_coords[i as usize];
+                    }
+                    g(&buffer[..chunk.len()], &uv_buffer[..chunk.len()]);
+                }
+            })
+        });
+    }
+
+    /// Renders list of 2d triangles using a color per vertex.
+    ///
+    /// Colors are interpolated linearly across each triangle.
+    ///
+    /// The back-end calls the closure with a closure to receive vertices.
+    /// First, the back-end sets up shaders and such to prepare.
+    /// Then it calls the closure, which calls back with chunks of vertices.
+    /// The number of vertices per chunk never exceeds
+    /// `BACK_END_MAX_VERTEX_COUNT`.
+    /// Vertex positions are encoded `[[x0, y0], [x1, y1], ...]`.
+    /// Colors are encoded `[[r0, g0, b0, a0], [r1, g1, b1, a1], ...]`.
+    ///
+    /// Chunks uses separate buffer for vertex positions and colors.
+    /// Arguments are `|vertices: &[f32], colors: &[f32]`.
+    ///
+    /// The default implementation approximates the interpolation
+    /// by splitting triangles into smaller triangles of solid color,
+    /// which are rendered with `tri_list`,
+    /// batching neighbor triangles of the same color.
+    /// Back-ends should override this method for better quality and performance.
+    ///
+    /// Color space is sRGB.
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]]))
+    {
+        use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+        let mut vertices: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        let mut color = [0.0; 4];
+        let mut offset = 0;
+        {
+            let mut push = |xy: &[[f32; 2]], c: &[f32; 4]| {
+                if offset > 0 && (*c != color || offset + 3 > BUFFER_SIZE) {
+                    self.tri_list(draw_state, &color, |f| f(&vertices[..offset]));
+                    offset = 0;
+                }
+                color = *c;
+                vertices[offset..offset + 3].copy_from_slice(xy);
+                offset += 3;
+            };
+            f(&mut |xy: &[[f32; 2]], c: &[[f32; 4]]| {
+                for (xy, c) in xy.chunks(3).zip(c.chunks(3)) {
+                    if xy.len() != 3 || c.len() != 3 {
+                        continue;
+                    }
+                    if c[0] == c[1] && c[1] == c[2] {
+                        push(xy, &c[0]);
+                    } else {
+                        triangulation::with_subdivided_tri_c([xy[0], xy[1], xy[2]],
+                                                             [c[0], c[1], c[2]],
+                                                             |xy, color| push(xy, color));
+                    }
+                }
+            });
+        }
+        if offset > 0 {
+            self.tri_list(draw_state, &color, |f| f(&vertices[..offset]));
+        }
+    }
+
+    /// Renders list of 2d triangles using a texture and a color per vertex.
+    ///
+    /// The texture color gets multiplied with the color,
+    /// which is interpolated linearly across each triangle.
+    ///
+    /// The back-end calls the closure with a closure to receive vertices.
+    /// First, the back-end sets up shaders and such to prepare.
+    /// Then it calls the closure, which calls back with chunks of vertices.
+    /// The number of vertices per chunk never exceeds
+    /// `BACK_END_MAX_VERTEX_COUNT`.
+    /// Vertex positions are encoded `[[x0, y0], [x1, y1], ...]`.
+    /// Texture coordinates are encoded `[[u0, v0], [u1, v1], ...]`.
+    /// Colors are encoded `[[r0, g0, b0, a0], [r1, g1, b1, a1], ...]`.
+    ///
+    /// Chunks uses separate buffer for vertex positions, texture coordinates and colors.
+    /// Arguments are `|vertices: &[f32], texture_coords: &[f32], colors: &[f32]`.
+    ///
+    /// The default implementation renders triangles of a single color
+    /// with `tri_list_uv`, batching neighbor triangles of the same color.
+    /// Other triangles are split into smaller triangles of solid color.
+    /// Back-ends should override this method for better quality and performance.
+    ///
+    /// Color space is sRGB.
+    fn tri_list_uv_c<F>(&mut self,
+                        draw_state: &DrawState,
+                        texture: &<Self as Graphics>::Texture,
+                        mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]]))
+    {
+        use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+        let mut vertices: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        let mut uvs: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+        let mut color = [0.0; 4];
+        let mut offset = 0;
+        f(&mut |xy: &[[f32; 2]], uv: &[[f32; 2]], c: &[[f32; 4]]| {
+            for ((xy, uv), c) in xy.chunks(3).zip(uv.chunks(3)).zip(c.chunks(3)) {
+                if xy.len() != 3 || uv.len() != 3 || c.len() != 3 {
+                    continue;
+                }
+                let solid = c[0] == c[1] && c[1] == c[2];
+                if offset > 0 && (!solid || c[0] != color || offset + 3 > BUFFER_SIZE) {
+                    self.tri_list_uv(draw_state,
+                                     &color,
+                                     texture,
+                                     |f| f(&vertices[..offset], &uvs[..offset]));
+                    offset = 0;
+                }
+                if solid {
+                    color = c[0];
+                    vertices[offset..offset + 3].copy_from_slice(xy);
+                    uvs[offset..offset + 3].copy_from_slice(uv);
+                    offset += 3;
+                } else {
+                    triangulation::with_subdivided_tri_uv_c([xy[0], xy[1], xy[2]],
+                                                            [uv[0], uv[1], uv[2]],
+                                                            [c[0], c[1], c[2]],
+                                                            |xy, uv, color| {
+                        self.tri_list_uv(draw_state, color, texture, |f| f(xy, uv))
+                    });
+                }
+            }
+        });
+        if offset > 0 {
+            self.tri_list_uv(draw_state,
+                             &color,
+                             texture,
+                             |f| f(&vertices[..offset], &uvs[..offset]));
+        }
+    }
+
     /// Draws a rectangle.
     ///
     /// Can be overriden in the back-end for higher performance.
@@ -155,6 +382,20 @@ pub trait Graphics: Sized {
         p.draw_tween_lerp_tri(polygons, tween_factor, draw_state, transform, self);
     }
 
+    /// Draws a polygon with multiple contours.
+    ///
+    /// Can be overridden in the back-end for higher performance.
+    ///
+    /// Instead of calling this directly, use `ComplexPolygon::draw`.
+    #[inline(always)]
+    fn complex_polygon(&mut self,
+                       p: &ComplexPolygon,
+                       contours: types::Polygons,
+                       draw_state: &DrawState,
+                       transform: Matrix2d) {
+        p.draw_tri(contours, draw_state, transform, self);
+    }
+
     /// Draws image.
     ///
     /// Can be overridden in the back-end for higher performance.
@@ -197,6 +438,20 @@ pub trait Graphics: Sized {
         l.draw_tri(line, draw_state, transform, self);
     }
 
+    /// Draws polyline.
+    ///
+    /// Can be overridden in the back-end for higher performance.
+    ///
+    /// Instead of calling this directly, use `Polyline::draw`.
+    #[inline(always)]
+    fn polyline(&mut self,
+                p: &Polyline,
+                points: types::Polygon,
+                draw_state: &DrawState,
+                transform: Matrix2d) {
+        p.draw_tri(points, draw_state, transform, self);
+    }
+
     /// Draws circle arc.
     ///
     /// Can be overriden in the back-end for higher performance.
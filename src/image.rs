@@ -164,9 +164,25 @@ pub fn draw_many<G>(rects: &[(Rectangle, SourceRectangle)],
                     g: &mut G)
     where G: Graphics
 {
-    g.tri_list_uv(draw_state, &color, texture, |f| for r in rects {
-        f(&triangulation::rect_tri_list_xy(transform, r.0),
-          &triangulation::rect_tri_list_uv(texture, r.1))
+    use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+    use triangulation::RECT_QUAD_INDICES;
+
+    const QUADS: usize = BUFFER_SIZE / 6;
+
+    let mut vertices: Vec<[f32; 2]> = Vec::with_capacity(4 * QUADS);
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(4 * QUADS);
+    let mut indices: Vec<u16> = Vec::with_capacity(6 * QUADS);
+    g.tri_list_uv_indexed(draw_state, &color, texture, |f| for chunk in rects.chunks(QUADS) {
+        vertices.clear();
+        uvs.clear();
+        indices.clear();
+        for r in chunk {
+            let offset = vertices.len() as u16;
+            vertices.extend_from_slice(&triangulation::rect_quad_xy(transform, r.0));
+            uvs.extend_from_slice(&triangulation::rect_quad_uv(texture, r.1));
+            indices.extend(RECT_QUAD_INDICES.iter().map(|&i| offset + i));
+        }
+        f(&vertices, &uvs, &indices)
     });
 }
 
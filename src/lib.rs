@@ -32,7 +32,11 @@ extern crate vecmath;
 extern crate texture;
 extern crate read_color;
 extern crate interpolation;
+extern crate unicode_segmentation;
 extern crate viewport;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
 
 pub use texture::ImageSize;
 pub use viewport::Viewport;
@@ -44,10 +48,12 @@ pub use transformed::Transformed;
 pub use colored::Colored;
 pub use rectangle::Rectangle;
 pub use line::Line;
+pub use polyline::Polyline;
 pub use ellipse::Ellipse;
 pub use circle_arc::CircleArc;
 pub use image::Image;
 pub use polygon::Polygon;
+pub use complex_polygon::ComplexPolygon;
 pub use text::Text;
 pub use context::Context;
 pub use draw_state::DrawState;
@@ -71,7 +77,11 @@ pub mod character;
 pub mod context;
 pub mod color;
 pub mod polygon;
+pub mod complex_polygon;
+pub mod path;
+pub mod gradient;
 pub mod line;
+pub mod polyline;
 pub mod circle_arc;
 pub mod ellipse;
 pub mod rectangle;
@@ -79,11 +89,19 @@ pub mod image;
 pub mod types;
 pub mod modular_index;
 pub mod text;
+pub mod text_layout;
+pub mod rich_text;
+pub mod text_path;
 pub mod triangulation;
 pub mod math;
 pub mod deform;
 pub mod grid;
 pub mod glyph_cache;
+pub mod software;
+pub mod svg;
+pub mod recorder;
+#[cfg(test)]
+mod mock;
 
 pub mod radians {
     //! Reexport radians helper trait from vecmath
@@ -2,7 +2,17 @@
 
 use {types, triangulation, DrawState, Graphics};
 use types::{Color, Radius};
-use math::{Matrix2d, Scalar};
+use math::{Matrix2d, Scalar, Vec2d};
+use polyline::Join;
+
+/// The maximum number of lengths in a dash pattern.
+pub const MAX_DASH_LENGTHS: usize = 8;
+
+/// The maximum number of times a dash pattern repeats along a stroke.
+///
+/// Strokes where the pattern repeats more often are not split,
+/// which limits the number of dashes of tiny patterns.
+pub const MAX_DASH_REPEATS: usize = 1 << 16;
 
 /// The shape of the line
 #[derive(Copy, Clone)]
@@ -15,6 +25,134 @@ pub enum Shape {
     Bevel,
 }
 
+/// A dash pattern
+///
+/// The pattern alternates between lengths that are drawn and lengths
+/// that are skipped, measured in local units along the stroke.
+/// Each dash is drawn with the caps of the stroke,
+/// such that zero lengths with round caps draw dots.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dash {
+    lengths: [Scalar; MAX_DASH_LENGTHS],
+    count: usize,
+    /// The distance into the pattern where the stroke starts
+    pub offset: Scalar,
+}
+
+impl Dash {
+    /// Creates a new dash pattern from alternating on and off lengths.
+    ///
+    /// An odd number of lengths is repeated to get an even number,
+    /// like in SVG.
+    ///
+    /// Panics if there are more than `MAX_DASH_LENGTHS` lengths after repeating.
+    pub fn new(lengths: &[Scalar]) -> Dash {
+        let count = if lengths.len() % 2 == 1 { 2 * lengths.len() } else { lengths.len() };
+        assert!(count <= MAX_DASH_LENGTHS,
+                "A dash pattern can not have more than {} lengths",
+                MAX_DASH_LENGTHS);
+        let mut res = Dash {
+            lengths: [0.0; MAX_DASH_LENGTHS],
+            count,
+            offset: 0.0,
+        };
+        for i in 0..count {
+            res.lengths[i] = lengths[i % lengths.len()].max(0.0);
+        }
+        res
+    }
+
+    /// Creates a new pattern of dots with a distance between them.
+    ///
+    /// The dots are visible with round caps.
+    pub fn dotted(distance: Scalar) -> Dash {
+        Dash::new(&[0.0, distance])
+    }
+
+    /// Sets offset.
+    pub fn offset(mut self, value: Scalar) -> Self {
+        self.offset = value;
+        self
+    }
+
+    /// Returns the on and off lengths.
+    pub fn lengths(&self) -> &[Scalar] {
+        &self.lengths[..self.count]
+    }
+
+    /// Splits a polyline into dashes.
+    ///
+    /// Returns the points of each dash.
+    /// A dash of zero length has a single point.
+    /// When the pattern has no length, or repeats more than `MAX_DASH_REPEATS` times,
+    /// the polyline is returned as a single dash.
+    ///
+    /// A closed polyline continues from the last point to the first point,
+    /// and the dash through the first point is returned as one dash.
+    /// A closed polyline without gaps is a single dash ending at its first point.
+    pub fn split(&self, points: &[Vec2d], closed: bool) -> Vec<Vec<Vec2d>> {
+        let mut points = points.to_vec();
+        if closed && !points.is_empty() {
+            let first = points[0];
+            points.push(first);
+        }
+        let total: Scalar = self.lengths().iter().sum();
+        let length: Scalar = points.windows(2)
+            .map(|w| ((w[1][0] - w[0][0]).powi(2) + (w[1][1] - w[0][1]).powi(2)).sqrt())
+            .sum();
+        if total <= 0.0 || points.is_empty() || length / total > MAX_DASH_REPEATS as Scalar {
+            return vec![points];
+        }
+
+        // Find where in the pattern the stroke starts.
+        let mut ind = 0;
+        let mut remaining = (self.offset % total + total) % total;
+        while remaining >= self.lengths[ind] && remaining > 0.0 {
+            remaining -= self.lengths[ind];
+            ind = (ind + 1) % self.count;
+        }
+        remaining = self.lengths[ind] - remaining;
+        let starts_on = ind % 2 == 0;
+
+        let mut res = vec![];
+        let mut dash = vec![points[0]];
+        for w in points.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+            let len = (dx * dx + dy * dy).sqrt();
+            if len == 0.0 {
+                continue;
+            }
+            let mut pos = 0.0;
+            while len - pos >= remaining {
+                pos += remaining;
+                let p = [a[0] + dx * pos / len, a[1] + dy * pos / len];
+                if ind % 2 == 0 {
+                    dash.push(p);
+                    res.push(dash);
+                    dash = vec![];
+                } else {
+                    dash = vec![p];
+                }
+                ind = (ind + 1) % self.count;
+                remaining = self.lengths[ind];
+            }
+            remaining -= len - pos;
+            if ind % 2 == 0 {
+                dash.push(b);
+            }
+        }
+        if ind % 2 == 0 && !dash.is_empty() {
+            if closed && starts_on && !res.is_empty() {
+                let first = res.remove(0);
+                dash.extend_from_slice(&first[1..]);
+            }
+            res.push(dash);
+        }
+        res
+    }
+}
+
 /// A colored line with a default border radius
 #[derive(Copy, Clone)]
 pub struct Line {
@@ -24,6 +162,8 @@ pub struct Line {
     pub radius: Radius,
     /// The line shape
     pub shape: Shape,
+    /// The dash pattern
+    pub dash: Option<Dash>,
 }
 
 impl Line {
@@ -33,6 +173,7 @@ impl Line {
             color: color,
             radius: radius,
             shape: Shape::Square,
+            dash: None,
         }
     }
 
@@ -42,6 +183,7 @@ impl Line {
             color: color,
             radius: radius,
             shape: Shape::Round,
+            dash: None,
         }
     }
 
@@ -69,6 +211,18 @@ impl Line {
         self
     }
 
+    /// Sets dash pattern.
+    pub fn dash(mut self, value: Dash) -> Self {
+        self.dash = Some(value);
+        self
+    }
+
+    /// Sets optional dash pattern.
+    pub fn maybe_dash(mut self, value: Option<Dash>) -> Self {
+        self.dash = value;
+        self
+    }
+
     /// Draws line using default method.
     #[inline(always)]
     pub fn draw<L: Into<types::Line>, G>(&self,
@@ -90,6 +244,24 @@ impl Line {
         where G: Graphics
     {
         let line = line.into();
+        if let Some(dash) = self.dash {
+            if self.color[3] == 0.0 {
+                return;
+            }
+            let points = [[line[0], line[1]], [line[2], line[3]]];
+            g.tri_list(draw_state, &self.color, |f| {
+                for points in dash.split(&points, false) {
+                    triangulation::with_polyline_tri_list(self.shape,
+                                                          Join::Bevel,
+                                                          false,
+                                                          transform,
+                                                          &points,
+                                                          self.radius,
+                                                          |vertices| f(vertices))
+                }
+            });
+            return;
+        }
         match self.shape {
             Shape::Square => {
                 g.tri_list(draw_state, &self.color, |f| {
@@ -162,4 +334,35 @@ mod test {
             .shape(Shape::Round)
             .hue_deg(1.0);
     }
+
+    #[test]
+    fn test_dash() {
+        let dash = Dash::new(&[2.0, 1.0]).offset(-0.5);
+        let dashes = dash.split(&[[0.0, 0.0], [5.0, 0.0], [5.0, 1.0]], false);
+        assert_eq!(dashes,
+                   vec![vec![[0.5, 0.0], [2.5, 0.0]],
+                        vec![[3.5, 0.0], [5.0, 0.0], [5.0, 0.5]]]);
+        assert_eq!(Dash::new(&[3.0]).lengths(), &[3.0, 3.0]);
+        let dots = Dash::dotted(1.0).split(&[[0.0, 0.0], [2.0, 0.0]], false);
+        assert_eq!(dots,
+                   vec![vec![[0.0, 0.0], [0.0, 0.0]],
+                        vec![[1.0, 0.0], [1.0, 0.0]],
+                        vec![[2.0, 0.0], [2.0, 0.0]]]);
+        // Patterns too small to split into dashes.
+        let line = [[0.0, 0.0], [1.0, 0.0]];
+        assert_eq!(Dash::new(&[1e-12]).split(&line, false), vec![line.to_vec()]);
+    }
+
+    #[test]
+    fn test_dash_closed() {
+        let square = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        // The dash through the first point is joined.
+        let dashes = Dash::new(&[3.0, 1.0]).offset(1.0).split(&square, true);
+        assert_eq!(dashes,
+                   vec![vec![[2.0, 1.0], [2.0, 2.0], [0.0, 2.0]],
+                        vec![[0.0, 1.0], [0.0, 0.0], [2.0, 0.0]]]);
+        // Without gaps, the dash ends where it starts.
+        let dashes = Dash::new(&[10.0, 1.0]).split(&square, true);
+        assert_eq!(dashes, vec![vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0], [0.0, 0.0]]]);
+    }
 }
@@ -0,0 +1,158 @@
+//! A configurable character cache for tests.
+
+use character::{Character, CharacterCache};
+use math::{Scalar, Vec2d};
+use recorder::Texture;
+use types::{FontSize, SourceRectangle};
+
+/// A font where every character has the same glyph.
+///
+/// The metrics are for font size 10 and scale with the font size,
+/// except for kerning.
+pub struct Font {
+    /// The number of calls to `character` and `subpixel_character`.
+    pub lookups: usize,
+    texture: Texture,
+    upper_case: Option<Texture>,
+    offset: Vec2d,
+    advance: Scalar,
+    atlas: SourceRectangle,
+    image_size: Vec2d,
+    kernings: Vec<(char, char, Scalar)>,
+    whole: bool,
+    subpixel: bool,
+    distance_field: Option<Scalar>,
+}
+
+impl Font {
+    /// Creates a font with glyphs 10 wide and an 8x8 image on the baseline.
+    pub fn new(texture: Texture) -> Font {
+        Font {
+            lookups: 0,
+            texture,
+            upper_case: None,
+            offset: [0.0, 8.0],
+            advance: 10.0,
+            atlas: [0.0, 0.0, 8.0, 8.0],
+            image_size: [8.0, 8.0],
+            kernings: vec![],
+            whole: false,
+            subpixel: false,
+            distance_field: None,
+        }
+    }
+
+    /// A builder method drawing upper case characters from another texture.
+    pub fn upper_case(mut self, texture: Texture) -> Font {
+        self.upper_case = Some(texture);
+        self
+    }
+
+    /// A builder method setting the offset of the image from the pen position.
+    pub fn offset(mut self, offset: Vec2d) -> Font {
+        self.offset = offset;
+        self
+    }
+
+    /// A builder method setting the advance of the pen.
+    pub fn advance(mut self, advance: Scalar) -> Font {
+        self.advance = advance;
+        self
+    }
+
+    /// A builder method setting the rectangle of the glyph in the texture,
+    /// and the size it is drawn at.
+    pub fn atlas(mut self, atlas: SourceRectangle, image_size: Vec2d) -> Font {
+        self.atlas = atlas;
+        self.image_size = image_size;
+        self
+    }
+
+    /// A builder method adding a kerning pair.
+    pub fn kerning(mut self, first: char, second: char, amount: Scalar) -> Font {
+        self.kernings.push((first, second, amount));
+        self
+    }
+
+    /// A builder method returning characters using the whole texture,
+    /// without a texture id.
+    pub fn whole(mut self) -> Font {
+        self.whole = true;
+        self
+    }
+
+    /// A builder method putting the image at a whole pixel for subpixel positions.
+    pub fn subpixel(mut self) -> Font {
+        self.subpixel = true;
+        self
+    }
+
+    /// A builder method making the images signed distance fields with a spread.
+    pub fn distance_field(mut self, spread: Scalar) -> Font {
+        self.distance_field = Some(spread);
+        self
+    }
+
+    fn glyph<'a>(&'a self, font_size: FontSize, ch: char) -> Character<'a, Texture> {
+        let scale = font_size as Scalar / 10.0;
+        let texture = match self.upper_case {
+            Some(ref texture) if ch.is_uppercase() => texture,
+            _ => &self.texture,
+        };
+        let offset = [scale * self.offset[0], scale * self.offset[1]];
+        let size = [scale * self.advance, 0.0];
+        if self.whole {
+            return Character::new(offset, size, texture);
+        }
+        Character {
+            offset,
+            size,
+            atlas_offset: [self.atlas[0], self.atlas[1]],
+            atlas_size: [self.atlas[2], self.atlas[3]],
+            image_size: [scale * self.image_size[0], scale * self.image_size[1]],
+            texture,
+            texture_id: Some(texture.id),
+        }
+    }
+}
+
+impl CharacterCache for Font {
+    type Texture = Texture;
+    type Error = ();
+
+    fn character<'a>(&'a mut self,
+                     font_size: FontSize,
+                     ch: char)
+                     -> Result<Character<'a, Texture>, ()> {
+        self.lookups += 1;
+        Ok(self.glyph(font_size, ch))
+    }
+
+    fn subpixel_character<'a>(&'a mut self,
+                              font_size: FontSize,
+                              ch: char,
+                              x: Scalar)
+                              -> Result<Character<'a, Texture>, ()> {
+        self.lookups += 1;
+        let mut character = self.glyph(font_size, ch);
+        if self.subpixel {
+            character.offset[0] = (x + character.offset[0]).floor() - x;
+        }
+        Ok(character)
+    }
+
+    fn is_distance_field(&self) -> bool {
+        self.distance_field.is_some()
+    }
+
+    fn distance_field_spread(&self) -> Scalar {
+        self.distance_field.unwrap_or(0.0)
+    }
+
+    fn kerning(&mut self, _font_size: FontSize, first: char, second: char) -> Result<Scalar, ()> {
+        Ok(self.kernings.iter()
+            .find(|kerning| (kerning.0, kerning.1) == (first, second))
+            .map(|kerning| kerning.2)
+            .unwrap_or(0.0))
+    }
+}
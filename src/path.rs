@@ -0,0 +1,367 @@
+//! Draw paths made of lines and curves
+//!
+//! A `Path` is built from segments, similar to paths in SVG or HTML canvas:
+//!
+//! ```
+//! use graphics::path::Path;
+//!
+//! let path = Path::new()
+//!     .move_to([0.0, 0.0])
+//!     .line_to([100.0, 0.0])
+//!     .quad_to([150.0, 50.0], [100.0, 100.0])
+//!     .cubic_to([70.0, 130.0], [30.0, 70.0], [0.0, 100.0])
+//!     .close();
+//! let subpaths = path.flatten(0.5);
+//! assert_eq!(subpaths.len(), 1);
+//! assert!(subpaths[0].closed);
+//! ```
+//!
+//! Curves are flattened into lines before drawing.
+//! The tolerance of the flattening is derived from the scale of the transform,
+//! such that curves stay smooth when zooming in.
+
+use types::{Color, Radius};
+use {triangulation, ComplexPolygon, DrawState, Graphics, Polyline};
+use gradient::Gradient;
+use complex_polygon::FillRule;
+use math::{get_scale, identity, Matrix2d, Scalar, Vec2d};
+
+/// The default flattening tolerance in normalized device coordinates.
+///
+/// This is about a quarter pixel in a window that is 1000 pixels wide.
+pub const DEFAULT_TOLERANCE: Scalar = 0.0005;
+
+/// The maximum number of lines a single curve is flattened into.
+const MAX_CURVE_LINES: usize = 1000;
+
+/// A segment of a path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Segment {
+    /// Starts a new subpath at a point.
+    MoveTo(Vec2d),
+    /// Draws a line to a point.
+    LineTo(Vec2d),
+    /// Draws a quadratic Bézier curve, given a control point and an end point.
+    QuadTo(Vec2d, Vec2d),
+    /// Draws a cubic Bézier curve, given two control points and an end point.
+    CubicTo(Vec2d, Vec2d, Vec2d),
+    /// Draws a circle arc.
+    ///
+    /// A line is drawn from the current point to the start of the arc.
+    ArcTo {
+        /// The center of the circle.
+        center: Vec2d,
+        /// The radius of the circle.
+        radius: Radius,
+        /// The start angle in radians.
+        start: Scalar,
+        /// The end angle in radians.
+        end: Scalar,
+    },
+    /// Closes the current subpath with a line to its start.
+    Close,
+}
+
+/// A flattened part of a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subpath {
+    /// The points of the subpath.
+    pub points: Vec<Vec2d>,
+    /// Whether the subpath is closed.
+    pub closed: bool,
+}
+
+/// A path made of lines and curves
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    /// The segments of the path.
+    pub segments: Vec<Segment>,
+    /// The fill rule used when filling the path.
+    pub fill_rule: FillRule,
+    /// The flattening tolerance in normalized device coordinates.
+    pub tolerance: Scalar,
+}
+
+impl Default for Path {
+    fn default() -> Path {
+        Path::new()
+    }
+}
+
+impl Path {
+    /// Creates a new empty path.
+    pub fn new() -> Path {
+        Path {
+            segments: vec![],
+            fill_rule: FillRule::NonZero,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Starts a new subpath.
+    pub fn move_to(mut self, p: Vec2d) -> Self {
+        self.segments.push(Segment::MoveTo(p));
+        self
+    }
+
+    /// Adds a line.
+    pub fn line_to(mut self, p: Vec2d) -> Self {
+        self.segments.push(Segment::LineTo(p));
+        self
+    }
+
+    /// Adds a quadratic Bézier curve.
+    pub fn quad_to(mut self, control: Vec2d, p: Vec2d) -> Self {
+        self.segments.push(Segment::QuadTo(control, p));
+        self
+    }
+
+    /// Adds a cubic Bézier curve.
+    pub fn cubic_to(mut self, control1: Vec2d, control2: Vec2d, p: Vec2d) -> Self {
+        self.segments.push(Segment::CubicTo(control1, control2, p));
+        self
+    }
+
+    /// Adds a circle arc from start to end angle in radians.
+    ///
+    /// The arc goes clockwise on the screen when `end > start`.
+    pub fn arc_to(mut self, center: Vec2d, radius: Radius, start: Scalar, end: Scalar) -> Self {
+        self.segments.push(Segment::ArcTo {
+            center,
+            radius,
+            start,
+            end,
+        });
+        self
+    }
+
+    /// Closes the current subpath.
+    pub fn close(mut self) -> Self {
+        self.segments.push(Segment::Close);
+        self
+    }
+
+    /// Sets fill rule.
+    pub fn fill_rule(mut self, value: FillRule) -> Self {
+        self.fill_rule = value;
+        self
+    }
+
+    /// Sets flattening tolerance in normalized device coordinates.
+    pub fn tolerance(mut self, value: Scalar) -> Self {
+        self.tolerance = value;
+        self
+    }
+
+    /// Computes the flattening tolerance in path coordinates.
+    pub fn tolerance_for(&self, transform: Matrix2d) -> Scalar {
+        let scale = get_scale(transform);
+        let scale = scale[0].max(scale[1]);
+        if scale > 0.0 { self.tolerance / scale } else { 1.0 }
+    }
+
+    /// Flattens curves into lines.
+    ///
+    /// The tolerance is the maximum distance in path coordinates
+    /// between a curve and its lines.
+    /// Subpaths with less than two points are skipped.
+    pub fn flatten(&self, tolerance: Scalar) -> Vec<Subpath> {
+        let mut subpaths = vec![];
+        let mut points: Vec<Vec2d> = vec![];
+        let mut closed = false;
+        let finish = |points: &mut Vec<Vec2d>, closed: bool, subpaths: &mut Vec<Subpath>| {
+            if points.len() >= 2 {
+                subpaths.push(Subpath {
+                    points: points.clone(),
+                    closed,
+                });
+            }
+            points.clear();
+        };
+        for segment in &self.segments {
+            if closed {
+                // Continue from the start of the closed subpath.
+                let start = points[0];
+                finish(&mut points, true, &mut subpaths);
+                points.push(start);
+                closed = false;
+            }
+            match *segment {
+                Segment::MoveTo(p) => {
+                    finish(&mut points, false, &mut subpaths);
+                    points.push(p);
+                }
+                Segment::LineTo(p) => points.push(p),
+                Segment::QuadTo(c, p) => {
+                    let p0 = match points.last() {
+                        Some(&p0) => p0,
+                        None => c,
+                    };
+                    let dd = [p0[0] - 2.0 * c[0] + p[0], p0[1] - 2.0 * c[1] + p[1]];
+                    let n = curve_lines(0.25 * len(dd), tolerance);
+                    for i in 1..n + 1 {
+                        let t = i as Scalar / n as Scalar;
+                        let (a, b, d) = ((1.0 - t) * (1.0 - t), 2.0 * t * (1.0 - t), t * t);
+                        points.push([a * p0[0] + b * c[0] + d * p[0],
+                                     a * p0[1] + b * c[1] + d * p[1]]);
+                    }
+                }
+                Segment::CubicTo(c1, c2, p) => {
+                    let p0 = match points.last() {
+                        Some(&p0) => p0,
+                        None => c1,
+                    };
+                    let dd1 = [p0[0] - 2.0 * c1[0] + c2[0], p0[1] - 2.0 * c1[1] + c2[1]];
+                    let dd2 = [c1[0] - 2.0 * c2[0] + p[0], c1[1] - 2.0 * c2[1] + p[1]];
+                    let n = curve_lines(0.75 * len(dd1).max(len(dd2)), tolerance);
+                    for i in 1..n + 1 {
+                        let t = i as Scalar / n as Scalar;
+                        let s = 1.0 - t;
+                        let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+                        points.push([a * p0[0] + b * c1[0] + c * c2[0] + d * p[0],
+                                     a * p0[1] + b * c1[1] + c * c2[1] + d * p[1]]);
+                    }
+                }
+                Segment::ArcTo { center, radius, start, end } => {
+                    // Pick the angle step such that the sagitta is within tolerance.
+                    let radius = radius.abs();
+                    let step = if tolerance < radius {
+                        2.0 * (1.0 - tolerance / radius).acos()
+                    } else {
+                        ::std::f64::consts::PI
+                    };
+                    let n = ((end - start).abs() / step).ceil().max(1.0) as usize;
+                    let n = n.min(MAX_CURVE_LINES);
+                    for i in 0..n + 1 {
+                        let angle = start + (end - start) * i as Scalar / n as Scalar;
+                        points.push([center[0] + radius * angle.cos(),
+                                     center[1] + radius * angle.sin()]);
+                    }
+                }
+                Segment::Close => closed = !points.is_empty(),
+            }
+        }
+        finish(&mut points, closed, &mut subpaths);
+        subpaths
+    }
+
+    /// Fills path using the default method for polygons with multiple contours.
+    ///
+    /// Open subpaths are closed implicitly.
+    pub fn fill<G>(&self, color: Color, draw_state: &DrawState, transform: Matrix2d, g: &mut G)
+        where G: Graphics
+    {
+        let subpaths = self.flatten(self.tolerance_for(transform));
+        let contours: Vec<&[Vec2d]> = subpaths.iter().map(|s| &s.points[..]).collect();
+        ComplexPolygon::new(color)
+            .fill_rule(self.fill_rule)
+            .draw(&contours, draw_state, transform, g);
+    }
+
+    /// Fills path with a gradient.
+    ///
+    /// The gradient is in the same coordinates as the path.
+    pub fn fill_gradient<G>(&self,
+                            gradient: &Gradient,
+                            draw_state: &DrawState,
+                            transform: Matrix2d,
+                            g: &mut G)
+        where G: Graphics
+    {
+        let subpaths = self.flatten(self.tolerance_for(transform));
+        let contours: Vec<&[Vec2d]> = subpaths.iter().map(|s| &s.points[..]).collect();
+        let mut triangles = vec![];
+        triangulation::with_contours_tri_list(self.fill_rule,
+                                              identity(),
+                                              &contours,
+                                              |vertices| triangles.extend_from_slice(vertices));
+        g.tri_list_c(draw_state, |f| {
+            gradient.stream_tri_list_c(transform, &triangles, |vertices, colors| f(vertices, colors))
+        });
+    }
+
+    /// Strokes path using the default method for polylines.
+    ///
+    /// The color, radius, caps and joins are taken from the polyline.
+    /// Each subpath is drawn as a separate polyline.
+    pub fn stroke<G>(&self,
+                     polyline: &Polyline,
+                     draw_state: &DrawState,
+                     transform: Matrix2d,
+                     g: &mut G)
+        where G: Graphics
+    {
+        for subpath in self.flatten(self.tolerance_for(transform)) {
+            polyline.closed(subpath.closed).draw(&subpath.points, draw_state, transform, g);
+        }
+    }
+}
+
+/// Computes the number of lines for a curve from a bound of the flattening error
+/// when using one line.
+fn curve_lines(error: Scalar, tolerance: Scalar) -> usize {
+    let n = (error / tolerance).sqrt().ceil();
+    if n.is_nan() || n < 1.0 {
+        1
+    } else {
+        (n as usize).min(MAX_CURVE_LINES)
+    }
+}
+
+fn len(v: Vec2d) -> Scalar {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flatten() {
+        let path = Path::new()
+            .move_to([0.0, 0.0])
+            .line_to([10.0, 0.0])
+            .close()
+            .line_to([0.0, 10.0])
+            .move_to([5.0, 5.0]);
+        assert_eq!(path.flatten(0.1),
+                   vec![Subpath {
+                            points: vec![[0.0, 0.0], [10.0, 0.0]],
+                            closed: true,
+                        },
+                        Subpath {
+                            points: vec![[0.0, 0.0], [0.0, 10.0]],
+                            closed: false,
+                        }]);
+    }
+
+    #[test]
+    fn test_flatten_tolerance() {
+        let tolerance = 0.01;
+        let quad = Path::new().move_to([0.0, 0.0]).quad_to([50.0, 100.0], [100.0, 0.0]);
+        let points = &quad.flatten(tolerance)[0].points;
+        // The midpoints of the lines are close to the curve.
+        for w in points.windows(2) {
+            let mid = [(w[0][0] + w[1][0]) / 2.0, (w[0][1] + w[1][1]) / 2.0];
+            let t = mid[0] / 100.0;
+            assert!((200.0 * t * (1.0 - t) - mid[1]).abs() <= tolerance);
+        }
+        // A smaller tolerance gives more points.
+        assert!(quad.flatten(tolerance / 4.0)[0].points.len() > points.len());
+
+        let arc = Path::new().arc_to([50.0, 50.0], 50.0, 0.0, 3.0);
+        let points = &arc.flatten(tolerance)[0].points;
+        for w in points.windows(2) {
+            let mid = [(w[0][0] + w[1][0]) / 2.0 - 50.0, (w[0][1] + w[1][1]) / 2.0 - 50.0];
+            assert!(50.0 - len(mid) <= tolerance);
+        }
+        let last = points[points.len() - 1];
+        assert!((last[0] - 50.0 - 50.0 * (3.0 as Scalar).cos()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tolerance_for() {
+        let path = Path::new().tolerance(0.01);
+        assert_eq!(path.tolerance_for([[2.0, 0.0, 0.0], [0.0, 0.5, 0.0]]), 0.005);
+    }
+}
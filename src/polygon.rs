@@ -2,7 +2,8 @@
 
 use types::Color;
 use {types, triangulation, Graphics, DrawState};
-use math::{Matrix2d, Scalar};
+use math::{identity, Matrix2d, Scalar};
+use gradient::Gradient;
 
 /// A polygon
 #[derive(Copy, Clone)]
@@ -48,6 +49,26 @@ impl Polygon {
         });
     }
 
+    /// Draws polygon filled with a gradient instead of the color.
+    ///
+    /// The gradient is in the same coordinates as the polygon.
+    pub fn draw_gradient<G>(&self,
+                            gradient: &Gradient,
+                            polygon: types::Polygon,
+                            draw_state: &DrawState,
+                            transform: Matrix2d,
+                            g: &mut G)
+        where G: Graphics
+    {
+        let mut triangles = vec![];
+        triangulation::with_polygon_tri_list(identity(),
+                                             polygon,
+                                             |vertices| triangles.extend_from_slice(vertices));
+        g.tri_list_c(draw_state, |f| {
+            gradient.stream_tri_list_c(transform, &triangles, |vertices, colors| f(vertices, colors))
+        });
+    }
+
     /// Draws tweened polygon with linear interpolation, using default method.
     #[inline(always)]
     pub fn draw_tween_lerp<G>(&self,
@@ -0,0 +1,134 @@
+//! Draw polyline
+
+use {types, triangulation, DrawState, Graphics};
+use types::{Color, Radius};
+use line::Shape;
+use math::{Matrix2d, Scalar};
+
+/// The shape of corners where two segments meet
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Join {
+    /// Sharp corners, with a miter limit.
+    ///
+    /// The limit is the maximum ratio between the miter length
+    /// and the line width, like the SVG `stroke-miterlimit`.
+    /// Corners exceeding the limit are beveled.
+    Miter(Scalar),
+    /// Round corners
+    Round,
+    /// Cut off corners
+    Bevel,
+}
+
+/// A colored line through multiple points
+#[derive(Copy, Clone)]
+pub struct Polyline {
+    /// The line color
+    pub color: Color,
+    /// The line radius
+    pub radius: Radius,
+    /// The shape of the ends
+    pub shape: Shape,
+    /// The shape of the corners
+    pub join: Join,
+    /// Whether the last point connects to the first point
+    pub closed: bool,
+}
+
+impl Polyline {
+    /// Creates a new polyline with miter joins
+    pub fn new(color: Color, radius: Radius) -> Polyline {
+        Polyline {
+            color,
+            radius,
+            shape: Shape::Square,
+            join: Join::Miter(4.0),
+            closed: false,
+        }
+    }
+
+    /// Sets color.
+    pub fn color(mut self, value: Color) -> Self {
+        self.color = value;
+        self
+    }
+
+    /// Sets radius.
+    pub fn radius(mut self, value: Radius) -> Self {
+        self.radius = value;
+        self
+    }
+
+    /// Sets width.
+    pub fn width(mut self, value: types::Width) -> Self {
+        self.radius = 0.5 * value;
+        self
+    }
+
+    /// Sets shape of the ends.
+    pub fn shape(mut self, value: Shape) -> Self {
+        self.shape = value;
+        self
+    }
+
+    /// Sets join.
+    pub fn join(mut self, value: Join) -> Self {
+        self.join = value;
+        self
+    }
+
+    /// Sets whether the polyline is closed.
+    pub fn closed(mut self, value: bool) -> Self {
+        self.closed = value;
+        self
+    }
+
+    /// Draws polyline using default method.
+    #[inline(always)]
+    pub fn draw<G>(&self,
+                   points: types::Polygon,
+                   draw_state: &DrawState,
+                   transform: Matrix2d,
+                   g: &mut G)
+        where G: Graphics
+    {
+        g.polyline(self, points, draw_state, transform);
+    }
+
+    /// Draws polyline using triangulation.
+    pub fn draw_tri<G>(&self,
+                       points: types::Polygon,
+                       draw_state: &DrawState,
+                       transform: Matrix2d,
+                       g: &mut G)
+        where G: Graphics
+    {
+        if self.color[3] == 0.0 {
+            return;
+        }
+        g.tri_list(draw_state, &self.color, |f| {
+            triangulation::with_polyline_tri_list(self.shape,
+                                                  self.join,
+                                                  self.closed,
+                                                  transform,
+                                                  points,
+                                                  self.radius,
+                                                  |vertices| f(vertices))
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_polyline() {
+        let _polyline = Polyline::new([0.0; 4], 3.0)
+            .color([1.0; 4])
+            .width(2.0)
+            .shape(Shape::Round)
+            .join(Join::Bevel)
+            .closed(true);
+    }
+}
@@ -0,0 +1,467 @@
+//! Recording back-end that captures drawing as a display list.
+//!
+//! A `Recorder` stores every call to the back-end as a `Command`.
+//! The commands can be compared, stored and replayed onto any other
+//! back-end with `replay`.
+//!
+//! Enable the "serialize" feature to serialize commands with Serde.
+//!
+//! ```
+//! use graphics::recorder::{replay, Recorder};
+//! use graphics::{clear, rectangle, Context};
+//!
+//! let c = Context::new_abs(100.0, 100.0);
+//! let mut recorder = Recorder::new();
+//! clear([1.0; 4], &mut recorder);
+//! rectangle([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 10.0, 10.0], c.transform, &mut recorder);
+//!
+//! let mut copy = Recorder::new();
+//! replay(&recorder.commands, |_| None, &mut copy);
+//! assert_eq!(recorder.commands, copy.commands);
+//! ```
+
+use types::Color;
+use {DrawState, Graphics, ImageSize};
+
+/// A handle to a texture used in a recording.
+///
+/// When replaying, the handle is used to look up the texture
+/// of the other back-end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Texture {
+    /// An identifier chosen by the user.
+    pub id: usize,
+    /// The width of the texture.
+    pub width: u32,
+    /// The height of the texture.
+    pub height: u32,
+}
+
+impl Texture {
+    /// Creates a new texture handle.
+    pub fn new(id: usize, width: u32, height: u32) -> Texture {
+        Texture { id, width, height }
+    }
+}
+
+impl ImageSize for Texture {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// A recorded call to the back-end.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Command {
+    /// Clears background with a color.
+    ClearColor(Color),
+    /// Clears stencil buffer with a value.
+    ClearStencil(u8),
+    /// Renders list of 2d triangles using a solid color.
+    TriList {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The color of all vertices.
+        color: Color,
+        /// The vertices, one list per chunk.
+        chunks: Vec<Vec<[f32; 2]>>,
+    },
+    /// Renders list of 2d triangles using a color per vertex.
+    TriListC {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The vertices and colors, one per chunk.
+        chunks: Vec<ColorChunk>,
+    },
+    /// Renders list of 2d triangles using a color and a texture.
+    TriListUv {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The color of all vertices.
+        color: Color,
+        /// The texture.
+        texture: Texture,
+        /// The vertices and texture coordinates, one per chunk.
+        chunks: Vec<UvChunk>,
+    },
+    /// Renders list of 2d triangles using a color and a signed distance field texture.
+    TriListUvSdf {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The color of all vertices.
+        color: Color,
+        /// The distance field texture.
+        texture: Texture,
+        /// The alpha of the edge of the drawn shape.
+        threshold: f32,
+        /// The vertices and texture coordinates, one per chunk.
+        chunks: Vec<UvChunk>,
+    },
+    /// Renders list of 2d triangles using a texture and a color per vertex.
+    TriListUvC {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The texture.
+        texture: Texture,
+        /// The vertices, texture coordinates and colors, one per chunk.
+        chunks: Vec<UvColorChunk>,
+    },
+    /// Renders list of 2d triangles using a solid color and an index buffer.
+    TriListIndexed {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The color of all vertices.
+        color: Color,
+        /// The vertices and indices, one per chunk.
+        chunks: Vec<IndexedChunk>,
+    },
+    /// Renders list of 2d triangles using a color, a texture and an index buffer.
+    TriListUvIndexed {
+        /// The draw state.
+        draw_state: DrawState,
+        /// The color of all vertices.
+        color: Color,
+        /// The texture.
+        texture: Texture,
+        /// The vertices, texture coordinates and indices, one per chunk.
+        chunks: Vec<UvIndexedChunk>,
+    },
+}
+
+/// A chunk of vertices with colors.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ColorChunk {
+    /// The vertex positions.
+    pub vertices: Vec<[f32; 2]>,
+    /// The vertex colors.
+    pub colors: Vec<[f32; 4]>,
+}
+
+/// A chunk of vertices with texture coordinates.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UvChunk {
+    /// The vertex positions.
+    pub vertices: Vec<[f32; 2]>,
+    /// The texture coordinates.
+    pub texture_coords: Vec<[f32; 2]>,
+}
+
+/// A chunk of vertices with texture coordinates and colors.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UvColorChunk {
+    /// The vertex positions.
+    pub vertices: Vec<[f32; 2]>,
+    /// The texture coordinates.
+    pub texture_coords: Vec<[f32; 2]>,
+    /// The vertex colors.
+    pub colors: Vec<[f32; 4]>,
+}
+
+/// A chunk of vertices with triangle indices.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct IndexedChunk {
+    /// The vertex positions.
+    pub vertices: Vec<[f32; 2]>,
+    /// The triangle indices.
+    pub indices: Vec<u16>,
+}
+
+/// A chunk of vertices with texture coordinates and triangle indices.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UvIndexedChunk {
+    /// The vertex positions.
+    pub vertices: Vec<[f32; 2]>,
+    /// The texture coordinates.
+    pub texture_coords: Vec<[f32; 2]>,
+    /// The triangle indices.
+    pub indices: Vec<u16>,
+}
+
+/// A back-end that records commands.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    /// The recorded commands.
+    pub commands: Vec<Command>,
+}
+
+impl Recorder {
+    /// Creates a new empty recorder.
+    pub fn new() -> Recorder {
+        Recorder { commands: Vec::new() }
+    }
+
+    /// Removes all recorded commands.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+impl Graphics for Recorder {
+    type Texture = Texture;
+
+    fn clear_color(&mut self, color: Color) {
+        self.commands.push(Command::ClearColor(color));
+    }
+
+    fn clear_stencil(&mut self, value: u8) {
+        self.commands.push(Command::ClearStencil(value));
+    }
+
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]]| chunks.push(vertices.to_vec()));
+        self.commands.push(Command::TriList {
+            draw_state: *draw_state,
+            color: *color,
+            chunks,
+        });
+    }
+
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], colors: &[[f32; 4]]| {
+            chunks.push(ColorChunk {
+                vertices: vertices.to_vec(),
+                colors: colors.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListC {
+            draw_state: *draw_state,
+            chunks,
+        });
+    }
+
+    fn tri_list_uv<F>(&mut self,
+                      draw_state: &DrawState,
+                      color: &[f32; 4],
+                      texture: &Texture,
+                      mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            chunks.push(UvChunk {
+                vertices: vertices.to_vec(),
+                texture_coords: texture_coords.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListUv {
+            draw_state: *draw_state,
+            color: *color,
+            texture: *texture,
+            chunks,
+        });
+    }
+
+    fn tri_list_uv_sdf<F>(&mut self,
+                          draw_state: &DrawState,
+                          color: &[f32; 4],
+                          texture: &Texture,
+                          threshold: f32,
+                          mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            chunks.push(UvChunk {
+                vertices: vertices.to_vec(),
+                texture_coords: texture_coords.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListUvSdf {
+            draw_state: *draw_state,
+            color: *color,
+            texture: *texture,
+            threshold,
+            chunks,
+        });
+    }
+
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Texture, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]], colors: &[[f32; 4]]| {
+            chunks.push(UvColorChunk {
+                vertices: vertices.to_vec(),
+                texture_coords: texture_coords.to_vec(),
+                colors: colors.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListUvC {
+            draw_state: *draw_state,
+            texture: *texture,
+            chunks,
+        });
+    }
+
+    fn tri_list_indexed<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[u16]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], indices: &[u16]| {
+            chunks.push(IndexedChunk {
+                vertices: vertices.to_vec(),
+                indices: indices.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListIndexed {
+            draw_state: *draw_state,
+            color: *color,
+            chunks,
+        });
+    }
+
+    fn tri_list_uv_indexed<F>(&mut self,
+                              draw_state: &DrawState,
+                              color: &[f32; 4],
+                              texture: &Texture,
+                              mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[u16]))
+    {
+        let mut chunks = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]], indices: &[u16]| {
+            chunks.push(UvIndexedChunk {
+                vertices: vertices.to_vec(),
+                texture_coords: texture_coords.to_vec(),
+                indices: indices.to_vec(),
+            })
+        });
+        self.commands.push(Command::TriListUvIndexed {
+            draw_state: *draw_state,
+            color: *color,
+            texture: *texture,
+            chunks,
+        });
+    }
+}
+
+/// Replays commands onto a back-end.
+///
+/// The `textures` closure looks up the texture of the back-end
+/// for a recorded texture handle.
+/// Commands using a texture that is not found are skipped.
+pub fn replay<'a, G, F>(commands: &[Command], mut textures: F, g: &mut G)
+    where G: Graphics,
+          G::Texture: 'a,
+          F: FnMut(Texture) -> Option<&'a G::Texture>
+{
+    for command in commands {
+        match *command {
+            Command::ClearColor(color) => g.clear_color(color),
+            Command::ClearStencil(value) => g.clear_stencil(value),
+            Command::TriList { ref draw_state, ref color, ref chunks } => {
+                g.tri_list(draw_state, color, |f| {
+                    for vertices in chunks {
+                        f(vertices);
+                    }
+                });
+            }
+            Command::TriListC { ref draw_state, ref chunks } => {
+                g.tri_list_c(draw_state, |f| {
+                    for chunk in chunks {
+                        f(&chunk.vertices, &chunk.colors);
+                    }
+                });
+            }
+            Command::TriListUv { ref draw_state, ref color, texture, ref chunks } => {
+                if let Some(texture) = textures(texture) {
+                    g.tri_list_uv(draw_state, color, texture, |f| {
+                        for chunk in chunks {
+                            f(&chunk.vertices, &chunk.texture_coords);
+                        }
+                    });
+                }
+            }
+            Command::TriListUvSdf { ref draw_state, ref color, texture, threshold, ref chunks } => {
+                if let Some(texture) = textures(texture) {
+                    g.tri_list_uv_sdf(draw_state, color, texture, threshold, |f| {
+                        for chunk in chunks {
+                            f(&chunk.vertices, &chunk.texture_coords);
+                        }
+                    });
+                }
+            }
+            Command::TriListUvC { ref draw_state, texture, ref chunks } => {
+                if let Some(texture) = textures(texture) {
+                    g.tri_list_uv_c(draw_state, texture, |f| {
+                        for chunk in chunks {
+                            f(&chunk.vertices, &chunk.texture_coords, &chunk.colors);
+                        }
+                    });
+                }
+            }
+            Command::TriListIndexed { ref draw_state, ref color, ref chunks } => {
+                g.tri_list_indexed(draw_state, color, |f| {
+                    for chunk in chunks {
+                        f(&chunk.vertices, &chunk.indices);
+                    }
+                });
+            }
+            Command::TriListUvIndexed { ref draw_state, ref color, texture, ref chunks } => {
+                if let Some(texture) = textures(texture) {
+                    g.tri_list_uv_indexed(draw_state, color, texture, |f| {
+                        for chunk in chunks {
+                            f(&chunk.vertices, &chunk.texture_coords, &chunk.indices);
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Context, Image};
+
+    #[test]
+    fn test_replay() {
+        let c = Context::new_abs(64.0, 64.0);
+        let texture = Texture::new(3, 16, 16);
+        let mut recorder = Recorder::new();
+        recorder.clear_stencil(0);
+        Image::new().draw(&texture, &c.draw_state, c.transform, &mut recorder);
+        assert_eq!(recorder.commands.len(), 2);
+
+        let mut copy = Recorder::new();
+        replay(&recorder.commands, |t| if t.id == 3 { Some(&texture) } else { None }, &mut copy);
+        assert_eq!(recorder.commands, copy.commands);
+
+        let mut skipped = Recorder::new();
+        replay(&recorder.commands, |_| None, &mut skipped);
+        assert_eq!(skipped.commands, vec![Command::ClearStencil(0)]);
+    }
+
+    #[test]
+    fn test_draw_many() {
+        use image::draw_many;
+
+        let c = Context::new_abs(64.0, 64.0);
+        let texture = Texture::new(0, 16, 16);
+        let rects = vec![([0.0, 0.0, 8.0, 8.0], [0.0, 0.0, 16.0, 16.0]); 200];
+        let mut recorder = Recorder::new();
+        draw_many(&rects, [1.0; 4], &texture, &c.draw_state, c.transform, &mut recorder);
+        match recorder.commands[0] {
+            Command::TriListUvIndexed { ref chunks, .. } => {
+                assert_eq!(chunks.len(), 2);
+                assert_eq!(chunks[0].vertices.len(), 4 * 170);
+                assert_eq!(chunks[0].indices.len(), 6 * 170);
+                assert_eq!(chunks[1].indices.len(), 6 * 30);
+            }
+            _ => panic!("expected indexed triangles"),
+        }
+    }
+}
@@ -15,7 +15,10 @@
 
 use types::{Color, Radius, Resolution};
 use {types, triangulation, Graphics, DrawState};
-use math::{Matrix2d, Scalar};
+use math::{identity, Matrix2d, Scalar};
+use gradient::Gradient;
+use line::{self, Dash};
+use polyline::Join;
 
 pub use math::margin_rectangle as margin;
 
@@ -75,6 +78,8 @@ pub struct Border {
     /// The radius of the border. The half-width of the line by which border is
     /// drawn.
     pub radius: Radius,
+    /// The dash pattern, where each dash has square ends.
+    pub dash: Option<Dash>,
 }
 
 /// A filled rectangle
@@ -115,6 +120,7 @@ impl Rectangle {
             border: Some(Border {
                 color: color,
                 radius: radius,
+                dash: None,
             }),
         }
     }
@@ -130,6 +136,7 @@ impl Rectangle {
             border: Some(Border {
                 color: color,
                 radius: border_radius,
+                dash: None,
             }),
         }
     }
@@ -175,6 +182,49 @@ impl Rectangle {
         g.rectangle(self, rectangle, draw_state, transform);
     }
 
+    /// Draws the rectangle filled with a gradient instead of the color.
+    ///
+    /// The gradient is in the same coordinates as the rectangle.
+    /// The border is drawn using the default method.
+    pub fn draw_gradient<R: Into<types::Rectangle>, G>(&self,
+                                                       gradient: &Gradient,
+                                                       rectangle: R,
+                                                       draw_state: &DrawState,
+                                                       transform: Matrix2d,
+                                                       g: &mut G)
+        where G: Graphics
+    {
+        let rectangle = rectangle.into();
+        let mut triangles = vec![];
+        match self.shape {
+            Shape::Square => {
+                triangles.extend_from_slice(&triangulation::rect_tri_list_xy(identity(), rectangle));
+            }
+            Shape::Round(round_radius, resolution) => {
+                triangulation::with_round_rectangle_tri_list(resolution,
+                                                             identity(),
+                                                             rectangle,
+                                                             round_radius,
+                                                             |vertices| {
+                    triangles.extend_from_slice(vertices)
+                });
+            }
+            Shape::Bevel(bevel_radius) => {
+                triangulation::with_round_rectangle_tri_list(2,
+                                                             identity(),
+                                                             rectangle,
+                                                             bevel_radius,
+                                                             |vertices| {
+                    triangles.extend_from_slice(vertices)
+                });
+            }
+        }
+        g.tri_list_c(draw_state, |f| {
+            gradient.stream_tri_list_c(transform, &triangles, |vertices, colors| f(vertices, colors))
+        });
+        self.draw_border(rectangle, draw_state, transform, g);
+    }
+
     /// Draws the rectangle using triangulation.
     ///
     /// This is the default implementation of draw() that will be used if `G`
@@ -215,10 +265,51 @@ impl Rectangle {
             }
         }
 
-        if let Some(Border { color, radius: border_radius }) = self.border {
+        self.draw_border(rectangle, draw_state, transform, g);
+    }
+
+    /// Draws the border using triangulation.
+    fn draw_border<G>(&self,
+                      rectangle: types::Rectangle,
+                      draw_state: &DrawState,
+                      transform: Matrix2d,
+                      g: &mut G)
+        where G: Graphics
+    {
+        if let Some(Border { color, radius: border_radius, dash }) = self.border {
             if color[3] == 0.0 {
                 return;
             }
+            if let Some(dash) = dash {
+                let outline = match self.shape {
+                    Shape::Square => {
+                        let (x, y, w, h) = (rectangle[0], rectangle[1], rectangle[2], rectangle[3]);
+                        vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]]
+                    }
+                    Shape::Round(round_radius, resolution) => {
+                        triangulation::round_rectangle_outline(resolution, rectangle, round_radius)
+                    }
+                    Shape::Bevel(bevel_radius) => {
+                        triangulation::round_rectangle_outline(2, rectangle, bevel_radius)
+                    }
+                };
+                g.tri_list(draw_state, &color, |f| {
+                    for points in dash.split(&outline, true) {
+                        // A dash without gaps around the outline ends where it starts.
+                        let n = points.len();
+                        let closed = n > 2 && points[0] == points[n - 1];
+                        let points = if closed { &points[..n - 1] } else { &points[..] };
+                        triangulation::with_polyline_tri_list(line::Shape::Square,
+                                                              Join::Miter(4.0),
+                                                              closed,
+                                                              transform,
+                                                              points,
+                                                              border_radius,
+                                                              |vertices| f(vertices))
+                    }
+                });
+                return;
+            }
             match self.shape {
                 Shape::Square => {
                     g.tri_list(draw_state, &color, |f| {
@@ -264,6 +355,7 @@ mod test {
             .border(Border {
                 color: [0.0; 4],
                 radius: 4.0,
+                dash: None,
             });
     }
 
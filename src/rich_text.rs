@@ -0,0 +1,262 @@
+//! Draw a line of text mixing colors, sizes and fonts
+//!
+//! A `RichText` is a list of spans, each drawn with its own color,
+//! font size and font.
+//! Fonts are selected by index into a slice of character caches,
+//! for example a regular and a bold glyph cache.
+//!
+//! All spans share the baseline at `y = 0`, like `Text::draw`.
+
+use character::{CharacterCache, FontMetrics, TextBounds};
+use math::{Matrix2d, Scalar};
+use types::{Color, FontSize};
+use {color, DrawState, Graphics, Text, Transformed};
+
+/// A part of rich text with the same style
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Span<'a> {
+    /// The text.
+    pub text: &'a str,
+    /// The color.
+    pub color: Color,
+    /// The font size.
+    pub font_size: FontSize,
+    /// The index of the character cache to draw with.
+    pub font: usize,
+}
+
+impl<'a> Span<'a> {
+    /// Creates a black span using the first font.
+    pub fn new(text: &'a str, font_size: FontSize) -> Span<'a> {
+        Span {
+            text,
+            color: color::BLACK,
+            font_size,
+            font: 0,
+        }
+    }
+
+    /// Sets the color.
+    pub fn color(mut self, value: Color) -> Self {
+        self.color = value;
+        self
+    }
+
+    /// Sets the index of the character cache to draw with.
+    pub fn font(mut self, value: usize) -> Self {
+        self.font = value;
+        self
+    }
+}
+
+/// A line of text made of spans
+///
+/// Characters next to each other are kerned
+/// when they have the same font and font size, even across spans.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RichText<'a> {
+    /// The spans, from left to right.
+    pub spans: Vec<Span<'a>>,
+}
+
+impl<'a> RichText<'a> {
+    /// Creates an empty rich text.
+    pub fn new() -> RichText<'a> {
+        RichText { spans: vec![] }
+    }
+
+    /// Adds a span.
+    pub fn span(mut self, value: Span<'a>) -> Self {
+        self.spans.push(value);
+        self
+    }
+
+    /// Returns the pen position at the start of every span, and the total width.
+    ///
+    /// Panics if a span uses a font outside `fonts`.
+    pub fn positions<C>(&self, fonts: &mut [C]) -> Result<(Vec<Scalar>, Scalar), C::Error>
+        where C: CharacterCache
+    {
+        self.each_char(fonts, |_, _, _, _| Ok(()))
+    }
+
+    /// Returns the width of the text.
+    pub fn width<C>(&self, fonts: &mut [C]) -> Result<Scalar, C::Error>
+        where C: CharacterCache
+    {
+        Ok(self.each_char(fonts, |_, _, _, _| Ok(()))?.1)
+    }
+
+    /// Returns the vertical metrics of the line.
+    ///
+    /// This is the highest ascent, the lowest descent
+    /// and the largest line gap of the spans.
+    /// Without spans, all metrics are zero.
+    pub fn metrics<C>(&self, fonts: &mut [C]) -> Result<FontMetrics, C::Error>
+        where C: CharacterCache
+    {
+        let mut res: Option<FontMetrics> = None;
+        for span in &self.spans {
+            let m = fonts[span.font].metrics(span.font_size)?;
+            res = Some(match res {
+                Some(r) => FontMetrics {
+                    ascent: r.ascent.max(m.ascent),
+                    descent: r.descent.min(m.descent),
+                    line_gap: r.line_gap.max(m.line_gap),
+                },
+                None => m,
+            });
+        }
+        Ok(res.unwrap_or(FontMetrics {
+            ascent: 0.0,
+            descent: 0.0,
+            line_gap: 0.0,
+        }))
+    }
+
+    /// Returns the ink and logical bounds of the text.
+    ///
+    /// See `CharacterCache::measure`.
+    pub fn measure<C>(&self, fonts: &mut [C]) -> Result<TextBounds, C::Error>
+        where C: CharacterCache
+    {
+        let metrics = self.metrics(fonts)?;
+        let mut ink: Option<[Scalar; 4]> = None;
+        let (_, width) = self.each_char(fonts, |span, cache, ch, x| {
+            if let Some(r) = cache.ink_bounds(span.font_size, ch)? {
+                let (x0, y0, x1, y1) = (x + r[0], r[1], x + r[0] + r[2], r[1] + r[3]);
+                ink = Some(match ink {
+                    Some(b) => [b[0].min(x0), b[1].min(y0), b[2].max(x1), b[3].max(y1)],
+                    None => [x0, y0, x1, y1],
+                });
+            }
+            Ok(())
+        })?;
+        let ink = ink.map(|b| [b[0], b[1], b[2] - b[0], b[3] - b[1]]).unwrap_or([0.0; 4]);
+        Ok(TextBounds {
+            ink,
+            logical: [0.0, -metrics.ascent, width, metrics.line_height()],
+        })
+    }
+
+    /// Draws the text, with the pen starting at the origin of the transform.
+    ///
+    /// Every span is drawn with `Text`.
+    pub fn draw<C, G>(&self,
+                      fonts: &mut [C],
+                      draw_state: &DrawState,
+                      transform: Matrix2d,
+                      g: &mut G)
+                      -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let (positions, _) = self.positions(fonts)?;
+        for (span, &x) in self.spans.iter().zip(&positions) {
+            Text::new_color(span.color, span.font_size).draw(span.text,
+                                                             &mut fonts[span.font],
+                                                             draw_state,
+                                                             transform.trans(x, 0.0),
+                                                             g)?;
+        }
+        Ok(())
+    }
+
+    /// Calls a closure with the pen position of every character.
+    ///
+    /// Returns the pen position at the start of every span, and the total width.
+    fn each_char<C, F>(&self, fonts: &mut [C], mut f: F) -> Result<(Vec<Scalar>, Scalar), C::Error>
+        where C: CharacterCache,
+              F: FnMut(&Span, &mut C, char, Scalar) -> Result<(), C::Error>
+    {
+        let mut positions = Vec::with_capacity(self.spans.len());
+        let mut x = 0.0;
+        // The previous character, with its font and size.
+        let mut prev: Option<(usize, FontSize, char)> = None;
+        for span in &self.spans {
+            let cache = &mut fonts[span.font];
+            // Kerning with the previous span moves the start of the span.
+            let mut start = None;
+            for ch in span.text.chars() {
+                if let Some((font, size, prev)) = prev {
+                    if font == span.font && size == span.font_size {
+                        x += cache.kerning(size, prev, ch)?;
+                    }
+                }
+                start = start.or(Some(x));
+                f(span, cache, ch, x)?;
+                x += cache.character(span.font_size, ch)?.width();
+                prev = Some((span.font, span.font_size, ch));
+            }
+            positions.push(start.unwrap_or(x));
+        }
+        Ok((positions, x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mock::Font;
+    use recorder::{Command, Recorder, Texture};
+    use Context;
+
+    /// Fonts with characters as wide as the font size, with "AV" kerned.
+    fn fonts() -> [Font; 2] {
+        let font = |id| {
+            Font::new(Texture::new(id, 8, 8))
+                .offset([0.0, 10.0])
+                .atlas([0.0, 0.0, 10.0, 10.0], [10.0, 10.0])
+                .kerning('A', 'V', -3.0)
+        };
+        [font(0), font(1)]
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut fonts = fonts();
+        let text = RichText::new()
+            .span(Span::new("AA", 10))
+            .span(Span::new("V", 10).color([1.0, 0.0, 0.0, 1.0]))
+            .span(Span::new("", 10))
+            .span(Span::new("AV", 20))
+            .span(Span::new("V", 20).font(1));
+        // Kerned within and across spans of the same font and size only.
+        let (positions, width) = text.positions(&mut fonts).unwrap();
+        assert_eq!(positions, vec![0.0, 17.0, 27.0, 27.0, 64.0]);
+        assert_eq!(width, 84.0);
+        assert_eq!(text.width(&mut fonts).unwrap(), 84.0);
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut fonts = fonts();
+        let text = RichText::new().span(Span::new("a ", 10)).span(Span::new("b", 20).font(1));
+        let metrics = text.metrics(&mut fonts).unwrap();
+        assert_eq!(metrics.ascent, 16.0);
+        assert_eq!(metrics.descent, -4.0);
+        let bounds = text.measure(&mut fonts).unwrap();
+        assert_eq!(bounds.ink, [0.0, -20.0, 40.0, 20.0]);
+        assert_eq!(bounds.logical, [0.0, -16.0, 40.0, 20.0]);
+        assert_eq!(RichText::new().measure(&mut fonts).unwrap().logical, [0.0; 4]);
+    }
+
+    #[test]
+    fn test_draw() {
+        let c = Context::new_abs(100.0, 100.0);
+        let mut fonts = fonts();
+        let mut recorder = Recorder::new();
+        RichText::new()
+            .span(Span::new("A", 10))
+            .span(Span::new("V", 10).color([1.0, 0.0, 0.0, 1.0]).font(1))
+            .draw(&mut fonts, &c.draw_state, c.transform, &mut recorder)
+            .unwrap();
+        let spans: Vec<(usize, [f32; 4], f32)> = recorder.commands.iter().map(|command| match *command {
+            Command::TriListUvIndexed { texture, color, ref chunks, .. } => {
+                (texture.id, color, chunks[0].vertices[0][0])
+            }
+            _ => panic!("expected indexed triangles"),
+        }).collect();
+        assert_eq!(spans, vec![(0, [0.0, 0.0, 0.0, 1.0], -1.0), (1, [1.0, 0.0, 0.0, 1.0], -0.8)]);
+    }
+}
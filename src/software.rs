@@ -0,0 +1,657 @@
+//! Software rasterizer back-end.
+//!
+//! Renders triangles into an in-memory RGBA8 framebuffer with a stencil
+//! buffer, without requiring a GPU or a window.
+//! This is useful for testing and for rendering on headless machines.
+//!
+//! ```
+//! use graphics::software::SoftwareGraphics;
+//! use graphics::{clear, rectangle};
+//!
+//! let mut g = SoftwareGraphics::new(64, 64);
+//! g.draw(|c, g| {
+//!     clear([1.0; 4], g);
+//!     rectangle([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 32.0, 32.0], c.transform, g);
+//! });
+//! assert_eq!(g.get_pixel(0, 0), [255, 0, 0, 255]);
+//! assert_eq!(g.get_pixel(63, 63), [255, 255, 255, 255]);
+//! ```
+
+use texture::{CreateTexture, Filter, Format, TextureSettings, UpdateTexture};
+use draw_state::{Blend, Stencil};
+use types::Color;
+use {Context, DrawState, Graphics, ImageSize, Viewport};
+
+/// A texture stored in CPU memory as RGBA8 pixels.
+#[derive(Clone)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    filter: Filter,
+}
+
+impl Texture {
+    /// Creates a texture from RGBA8 pixels, stored row by row from the top.
+    ///
+    /// Panics if the length of `data` does not match the size.
+    pub fn from_rgba8(data: Vec<u8>, width: u32, height: u32) -> Texture {
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        Texture {
+            width,
+            height,
+            data,
+            filter: Filter::Linear,
+        }
+    }
+
+    /// Sets the filter used when sampling the texture.
+    pub fn filter(mut self, value: Filter) -> Self {
+        self.filter = value;
+        self
+    }
+
+    /// Returns the RGBA8 pixels of the texture.
+    pub fn pixels(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        let x = x.max(0).min(self.width as i64 - 1) as usize;
+        let y = y.max(0).min(self.height as i64 - 1) as usize;
+        let i = (x + y * self.width as usize) * 4;
+        let d = &self.data[i..i + 4];
+        [d[0] as f32 / 255.0, d[1] as f32 / 255.0, d[2] as f32 / 255.0, d[3] as f32 / 255.0]
+    }
+
+    /// Samples the texture at a texture coordinate, clamping to the edges.
+    fn sample(&self, uv: [f32; 2]) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0.0; 4];
+        }
+        let x = uv[0] as f64 * self.width as f64;
+        let y = uv[1] as f64 * self.height as f64;
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let a = self.texel(x0, y0);
+                let b = self.texel(x0 + 1, y0);
+                let c = self.texel(x0, y0 + 1);
+                let d = self.texel(x0 + 1, y0 + 1);
+                let mut res = [0.0; 4];
+                for i in 0..4 {
+                    let top = a[i] + (b[i] - a[i]) * fx;
+                    let bottom = c[i] + (d[i] - c[i]) * fx;
+                    res[i] = top + (bottom - top) * fy;
+                }
+                res
+            }
+        }
+    }
+}
+
+impl ImageSize for Texture {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl CreateTexture<()> for Texture {
+    type Error = String;
+
+    fn create<S: Into<[u32; 2]>>(_factory: &mut (),
+                                 _format: Format,
+                                 memory: &[u8],
+                                 size: S,
+                                 settings: &TextureSettings)
+                                 -> Result<Self, Self::Error> {
+        let [w, h] = size.into();
+        let len = (w * h * 4) as usize;
+        if memory.len() < len {
+            return Err(format!("Expected {} bytes for a {}x{} texture, found {}",
+                               len, w, h, memory.len()));
+        }
+        Ok(Texture::from_rgba8(memory[..len].to_vec(), w, h).filter(settings.get_mag()))
+    }
+}
+
+impl UpdateTexture<()> for Texture {
+    type Error = String;
+
+    fn update<O, S>(&mut self,
+                    _factory: &mut (),
+                    _format: Format,
+                    memory: &[u8],
+                    offset: O,
+                    size: S)
+                    -> Result<(), Self::Error>
+        where O: Into<[u32; 2]>,
+              S: Into<[u32; 2]>
+    {
+        let [x, y] = offset.into();
+        let [w, h] = size.into();
+        if x + w > self.width || y + h > self.height {
+            return Err(format!("Update region {}x{} at ({}, {}) is outside {}x{} texture",
+                               w, h, x, y, self.width, self.height));
+        }
+        if memory.len() < (w * h * 4) as usize {
+            return Err(format!("Expected {} bytes for a {}x{} update, found {}",
+                               w * h * 4, w, h, memory.len()));
+        }
+        let row = (w * 4) as usize;
+        for iy in 0..h as usize {
+            let src = iy * row;
+            let dst = ((x as usize) + (y as usize + iy) * self.width as usize) * 4;
+            self.data[dst..dst + row].copy_from_slice(&memory[src..src + row]);
+        }
+        Ok(())
+    }
+}
+
+/// A back-end that rasterizes triangles into an RGBA8 framebuffer.
+///
+/// The framebuffer is stored row by row from the top.
+/// Vertices are received in normalized device coordinates,
+/// where `(-1.0, 1.0)` is the upper left corner of the framebuffer.
+/// Scissor rectangles are in pixels, with the origin in the upper left corner.
+pub struct SoftwareGraphics {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    stencil: Vec<u8>,
+}
+
+impl SoftwareGraphics {
+    /// Creates a new framebuffer filled with transparent black.
+    pub fn new(width: u32, height: u32) -> SoftwareGraphics {
+        let n = (width * height) as usize;
+        SoftwareGraphics {
+            width,
+            height,
+            pixels: vec![0; n * 4],
+            stencil: vec![0; n],
+        }
+    }
+
+    /// Gets the width of the framebuffer in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the framebuffer in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets a viewport covering the whole framebuffer.
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            rect: [0, 0, self.width as i32, self.height as i32],
+            draw_size: [self.width, self.height],
+            window_size: [self.width, self.height],
+        }
+    }
+
+    /// Calls a closure with a context set up for the whole framebuffer.
+    pub fn draw<F, U>(&mut self, f: F) -> U
+        where F: FnOnce(Context, &mut Self) -> U
+    {
+        let c = Context::new_viewport(self.viewport());
+        f(c, self)
+    }
+
+    /// Returns the RGBA8 pixels of the framebuffer.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Consumes the back-end and returns the RGBA8 pixels.
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Returns the stencil buffer, one value per pixel.
+    pub fn stencil(&self) -> &[u8] {
+        &self.stencil
+    }
+
+    /// Gets the color of a pixel.
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((x + y * self.width) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Rasterizes a triangle given in normalized device coordinates.
+    ///
+    /// The `shade` closure computes the source color from barycentric
+    /// coordinates of the pixel center.
+    ///
+    /// Pixels are sampled at their centers, and the top-left fill rule
+    /// makes sure that triangles sharing an edge never cover the same pixel.
+    fn rasterize<S>(&mut self, draw_state: &DrawState, tri: &[[f32; 2]], mut shade: S)
+        where S: FnMut([f32; 3]) -> [f32; 4]
+    {
+        let (w, h) = (self.width as f64, self.height as f64);
+        let to_screen = |p: [f32; 2]| [(p[0] as f64 + 1.0) * 0.5 * w, (1.0 - p[1] as f64) * 0.5 * h];
+        let p = [to_screen(tri[0]), to_screen(tri[1]), to_screen(tri[2])];
+
+        let area = edge(p[0], p[1], p[2]);
+        if area == 0.0 || area.is_nan() {
+            return;
+        }
+        // Make the triangle positively oriented, keeping track of
+        // which original vertex each barycentric coordinate belongs to.
+        let (p, order) = if area > 0.0 {
+            (p, [0, 1, 2])
+        } else {
+            ([p[0], p[2], p[1]], [0, 2, 1])
+        };
+        let area = area.abs();
+
+        let (mut x0, mut y0, mut x1, mut y1) = (0.0, 0.0, w, h);
+        if let Some(r) = draw_state.scissor {
+            x0 = (r[0] as f64).max(x0);
+            y0 = (r[1] as f64).max(y0);
+            x1 = (r[0].saturating_add(r[2]) as f64).min(x1);
+            y1 = (r[1].saturating_add(r[3]) as f64).min(y1);
+        }
+        let min_x = p[0][0].min(p[1][0]).min(p[2][0]).floor().max(x0);
+        let min_y = p[0][1].min(p[1][1]).min(p[2][1]).floor().max(y0);
+        let max_x = p[0][0].max(p[1][0]).max(p[2][0]).ceil().min(x1);
+        let max_y = p[0][1].max(p[1][1]).max(p[2][1]).ceil().min(y1);
+        if min_x >= max_x || min_y >= max_y {
+            return;
+        }
+
+        let top_left = [is_top_left(p[1], p[2]), is_top_left(p[2], p[0]), is_top_left(p[0], p[1])];
+        for y in min_y as u32..max_y as u32 {
+            for x in min_x as u32..max_x as u32 {
+                let c = [x as f64 + 0.5, y as f64 + 0.5];
+                let e = [edge(p[1], p[2], c), edge(p[2], p[0], c), edge(p[0], p[1], c)];
+                let inside = (0..3).all(|i| e[i] > 0.0 || (e[i] == 0.0 && top_left[i]));
+                if !inside {
+                    continue;
+                }
+
+                let ind = (x + y * self.width) as usize;
+                match draw_state.stencil {
+                    Some(Stencil::Clip(val)) => {
+                        self.stencil[ind] = val;
+                        continue;
+                    }
+                    Some(Stencil::Inside(val)) if self.stencil[ind] != val => continue,
+                    Some(Stencil::Outside(val)) if self.stencil[ind] == val => continue,
+                    _ => {}
+                }
+
+                let mut b = [0.0; 3];
+                for i in 0..3 {
+                    b[order[i]] = (e[i] / area) as f32;
+                }
+                let src = shade(b);
+                let dst = &mut self.pixels[ind * 4..ind * 4 + 4];
+                let res = blend(draw_state.blend, src, [dst[0] as f32 / 255.0,
+                                                        dst[1] as f32 / 255.0,
+                                                        dst[2] as f32 / 255.0,
+                                                        dst[3] as f32 / 255.0]);
+                for i in 0..4 {
+                    dst[i] = to_u8(res[i]);
+                }
+            }
+        }
+    }
+}
+
+impl Graphics for SoftwareGraphics {
+    type Texture = Texture;
+
+    fn clear_color(&mut self, color: Color) {
+        let c = [to_u8(color[0]), to_u8(color[1]), to_u8(color[2]), to_u8(color[3])];
+        for px in self.pixels.chunks_mut(4) {
+            px.copy_from_slice(&c);
+        }
+    }
+
+    fn clear_stencil(&mut self, value: u8) {
+        for s in &mut self.stencil {
+            *s = value;
+        }
+    }
+
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]]))
+    {
+        let color = *color;
+        f(&mut |vertices: &[[f32; 2]]| {
+            for tri in vertices.chunks(3).filter(|tri| tri.len() == 3) {
+                self.rasterize(draw_state, tri, |_| color);
+            }
+        });
+    }
+
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]]))
+    {
+        f(&mut |vertices: &[[f32; 2]], colors: &[[f32; 4]]| {
+            for (tri, c) in vertices.chunks(3).zip(colors.chunks(3)) {
+                if tri.len() != 3 || c.len() != 3 {
+                    continue;
+                }
+                self.rasterize(draw_state, tri, |b| {
+                    let mut color = [0.0; 4];
+                    for (i, v) in color.iter_mut().enumerate() {
+                        *v = b[0] * c[0][i] + b[1] * c[1][i] + b[2] * c[2][i];
+                    }
+                    color
+                });
+            }
+        });
+    }
+
+    fn tri_list_uv<F>(&mut self,
+                      draw_state: &DrawState,
+                      color: &[f32; 4],
+                      texture: &Texture,
+                      mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        let color = *color;
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            for (tri, uv) in vertices.chunks(3).zip(texture_coords.chunks(3)) {
+                if tri.len() != 3 || uv.len() != 3 {
+                    continue;
+                }
+                self.rasterize(draw_state, tri, |b| {
+                    let u = b[0] * uv[0][0] + b[1] * uv[1][0] + b[2] * uv[2][0];
+                    let v = b[0] * uv[0][1] + b[1] * uv[1][1] + b[2] * uv[2][1];
+                    let t = texture.sample([u, v]);
+                    [t[0] * color[0], t[1] * color[1], t[2] * color[2], t[3] * color[3]]
+                });
+            }
+        });
+    }
+
+    fn tri_list_uv_sdf<F>(&mut self,
+                          draw_state: &DrawState,
+                          color: &[f32; 4],
+                          texture: &Texture,
+                          threshold: f32,
+                          mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        let color = *color;
+        let (w, h) = (self.width as f32, self.height as f32);
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            for (tri, uv) in vertices.chunks(3).zip(texture_coords.chunks(3)) {
+                if tri.len() != 3 || uv.len() != 3 {
+                    continue;
+                }
+                let [du, dv] = match uv_derivatives(tri, uv, w, h) {
+                    Some(d) => d,
+                    None => continue,
+                };
+                self.rasterize(draw_state, tri, |b| {
+                    let u = b[0] * uv[0][0] + b[1] * uv[1][0] + b[2] * uv[2][0];
+                    let v = b[0] * uv[0][1] + b[1] * uv[1][1] + b[2] * uv[2][1];
+                    let t = texture.sample([u, v]);
+                    // Smooth the edge over the change of distance to the next pixels.
+                    let dx = texture.sample([u + du[0], v + dv[0]])[3] - t[3];
+                    let dy = texture.sample([u + du[1], v + dv[1]])[3] - t[3];
+                    let width = (dx.abs() + dy.abs()).max(1e-4);
+                    // `clamp` needs Rust 1.50.
+                    #[allow(clippy::manual_clamp)]
+                    let alpha = ((t[3] - threshold) / width + 0.5).max(0.0).min(1.0);
+                    [t[0] * color[0], t[1] * color[1], t[2] * color[2], alpha * color[3]]
+                });
+            }
+        });
+    }
+
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Texture, mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]]))
+    {
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]], colors: &[[f32; 4]]| {
+            let tris = vertices.chunks(3).zip(texture_coords.chunks(3)).zip(colors.chunks(3));
+            for ((tri, uv), c) in tris {
+                if tri.len() != 3 || uv.len() != 3 || c.len() != 3 {
+                    continue;
+                }
+                self.rasterize(draw_state, tri, |b| {
+                    let u = b[0] * uv[0][0] + b[1] * uv[1][0] + b[2] * uv[2][0];
+                    let v = b[0] * uv[0][1] + b[1] * uv[1][1] + b[2] * uv[2][1];
+                    let mut color = texture.sample([u, v]);
+                    for (i, t) in color.iter_mut().enumerate() {
+                        *t *= b[0] * c[0][i] + b[1] * c[1][i] + b[2] * c[2][i];
+                    }
+                    color
+                });
+            }
+        });
+    }
+}
+
+/// Computes twice the signed area of the triangle `a, b, c`.
+#[inline(always)]
+fn edge(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Returns true if the edge is a top or left edge of a positively oriented triangle.
+#[inline(always)]
+fn is_top_left(a: [f64; 2], b: [f64; 2]) -> bool {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    (dy == 0.0 && dx > 0.0) || dy < 0.0
+}
+
+/// Computes the change of texture coordinates per pixel to the right and down.
+///
+/// Returns `[[du/dx, du/dy], [dv/dx, dv/dy]]` for a triangle on a target of size `w, h`,
+/// or `None` if the triangle has no area.
+fn uv_derivatives(tri: &[[f32; 2]], uv: &[[f32; 2]], w: f32, h: f32) -> Option<[[f32; 2]; 2]> {
+    let to_screen = |p: [f32; 2]| [(p[0] + 1.0) * 0.5 * w, (1.0 - p[1]) * 0.5 * h];
+    let p = [to_screen(tri[0]), to_screen(tri[1]), to_screen(tri[2])];
+    let (a, b) = (p[1][0] - p[0][0], p[1][1] - p[0][1]);
+    let (c, d) = (p[2][0] - p[0][0], p[2][1] - p[0][1]);
+    let det = a * d - b * c;
+    if det == 0.0 || det.is_nan() {
+        return None;
+    }
+    let mut res = [[0.0; 2]; 2];
+    for (i, r) in res.iter_mut().enumerate() {
+        let (e1, e2) = (uv[1][i] - uv[0][i], uv[2][i] - uv[0][i]);
+        *r = [(e1 * d - e2 * b) / det, (e2 * a - e1 * c) / det];
+    }
+    Some(res)
+}
+
+#[inline(always)]
+// `clamp` needs Rust 1.50.
+#[allow(clippy::manual_clamp)]
+fn to_u8(v: f32) -> u8 {
+    (v.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// Combines a source color with a destination color.
+///
+/// See `draw_state::Blend` for the equations.
+fn blend(blend: Option<Blend>, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let (s, d) = (src, dst);
+    match blend {
+        None => s,
+        Some(Blend::Alpha) => {
+            let a = s[3];
+            [s[0] * a + d[0] * (1.0 - a),
+             s[1] * a + d[1] * (1.0 - a),
+             s[2] * a + d[2] * (1.0 - a),
+             s[3] + d[3]]
+        }
+        Some(Blend::Add) => [s[0] + d[0], s[1] + d[1], s[2] + d[2], s[3] + d[3]],
+        Some(Blend::Multiply) => [s[0] * d[0], s[1] * d[1], s[2] * d[2], s[3] * d[3]],
+        // Uses white as the constant color.
+        Some(Blend::Invert) => {
+            [s[0] * (1.0 - d[0]), s[1] * (1.0 - d[1]), s[2] * (1.0 - d[2]), d[3]]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {clear, Rectangle};
+
+    #[test]
+    fn test_shared_edges() {
+        let mut g = SoftwareGraphics::new(16, 16);
+        g.draw(|c, g| {
+            clear([0.0, 0.0, 0.0, 1.0], g);
+            Rectangle::new([1.0, 1.0, 1.0, 0.5]).draw([0.0, 0.0, 16.0, 16.0],
+                                                        &c.draw_state,
+                                                        c.transform,
+                                                        g);
+        });
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(g.get_pixel(x, y), [128, 128, 128, 255]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scissor() {
+        let mut g = SoftwareGraphics::new(8, 8);
+        g.draw(|c, g| {
+            let draw_state = c.draw_state.scissor([2, 2, 4, 4]);
+            Rectangle::new([1.0; 4]).draw([0.0, 0.0, 8.0, 8.0], &draw_state, c.transform, g);
+        });
+        assert_eq!(g.get_pixel(1, 1), [0, 0, 0, 0]);
+        assert_eq!(g.get_pixel(2, 2), [255; 4]);
+        assert_eq!(g.get_pixel(5, 5), [255; 4]);
+        assert_eq!(g.get_pixel(6, 6), [0, 0, 0, 0]);
+
+        // Scissor rectangles reaching past the largest coordinate.
+        g.draw(|c, g| {
+            let draw_state = c.draw_state.scissor([4, 4, !0, !0]);
+            Rectangle::new([1.0, 0.0, 0.0, 1.0]).draw([0.0, 0.0, 8.0, 8.0], &draw_state, c.transform, g);
+        });
+        assert_eq!(g.get_pixel(3, 3), [255; 4]);
+        assert_eq!(g.get_pixel(7, 7), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_stencil() {
+        let mut g = SoftwareGraphics::new(8, 8);
+        g.draw(|c, g| {
+            clear([0.0, 0.0, 0.0, 1.0], g);
+            let rect = Rectangle::new([1.0; 4]);
+            rect.draw([0.0, 0.0, 4.0, 8.0], &DrawState::new_clip(), c.transform, g);
+            rect.draw([0.0, 0.0, 8.0, 4.0], &DrawState::new_inside(), c.transform, g);
+            rect.color([1.0, 0.0, 0.0, 1.0])
+                .draw([0.0, 4.0, 8.0, 4.0], &DrawState::new_outside(), c.transform, g);
+        });
+        assert_eq!(g.get_pixel(0, 0), [255; 4]);
+        assert_eq!(g.get_pixel(7, 0), [0, 0, 0, 255]);
+        assert_eq!(g.get_pixel(0, 7), [0, 0, 0, 255]);
+        assert_eq!(g.get_pixel(7, 7), [255, 0, 0, 255]);
+        assert_eq!(g.stencil()[0], 255);
+    }
+
+    #[test]
+    fn test_blend() {
+        let s = [0.5, 0.5, 0.5, 0.5];
+        let d = [1.0, 0.0, 1.0, 1.0];
+        assert_eq!(blend(None, s, d), s);
+        assert_eq!(blend(Some(Blend::Alpha), s, d), [0.75, 0.25, 0.75, 1.5]);
+        assert_eq!(blend(Some(Blend::Add), s, d), [1.5, 0.5, 1.5, 1.5]);
+        assert_eq!(blend(Some(Blend::Multiply), s, d), [0.5, 0.0, 0.5, 0.5]);
+        assert_eq!(blend(Some(Blend::Invert), s, d), [0.0, 0.5, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_gradient() {
+        use gradient::Gradient;
+
+        let mut g = SoftwareGraphics::new(100, 10);
+        g.draw(|c, g| {
+            let gradient = Gradient::linear([0.0, 0.0], [100.0, 0.0])
+                .stop(0.0, [0.0, 0.0, 0.0, 1.0])
+                .stop(1.0, [1.0; 4]);
+            Rectangle::new([0.0; 4])
+                .draw_gradient(&gradient, [0.0, 0.0, 100.0, 10.0], &c.draw_state, c.transform, g);
+        });
+        assert_eq!(g.get_pixel(0, 5), [1, 1, 1, 255]);
+        assert_eq!(g.get_pixel(49, 5), [126, 126, 126, 255]);
+        assert_eq!(g.get_pixel(99, 5), [254, 254, 254, 255]);
+    }
+
+    #[test]
+    fn test_deform_colors() {
+        use deform::DeformGrid;
+
+        let texture = Texture::from_rgba8(vec![255; 4], 1, 1);
+        let mut grid = DeformGrid::new([0.0, 0.0, 8.0, 8.0], 1, 1);
+        grid.colors = vec![[1.0, 0.0, 0.0, 1.0]; 4];
+        let mut g = SoftwareGraphics::new(8, 8);
+        g.draw(|c, g| grid.draw_image(&texture, &c.draw_state, c.transform, g));
+        assert_eq!(g.get_pixel(4, 4), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_sdf() {
+        use triangulation::{rect_tri_list_uv, rect_tri_list_xy};
+
+        // The distance field of a disk with a radius of 3 texels.
+        let data = (0..8 * 8).flat_map(|i| {
+            let (x, y) = ((i % 8) as f32 + 0.5 - 4.0, (i / 8) as f32 + 0.5 - 4.0);
+            let d = 3.0 - (x * x + y * y).sqrt();
+            vec![255, 255, 255, to_u8(0.5 + d / 6.0)]
+        }).collect();
+        let texture = Texture::from_rgba8(data, 8, 8);
+        let mut g = SoftwareGraphics::new(32, 32);
+        g.draw(|c, g| {
+            clear([0.0, 0.0, 0.0, 1.0], g);
+            let xy = rect_tri_list_xy(c.transform, [0.0, 0.0, 32.0, 32.0]);
+            let uv = rect_tri_list_uv(&texture, [0.0, 0.0, 8.0, 8.0]);
+            g.tri_list_uv_sdf(&c.draw_state, &[1.0; 4], &texture, 0.5, |f| f(&xy, &uv));
+        });
+        // Scaled four times, the edge stays about a pixel wide.
+        for y in 0..32 {
+            for x in 0..32 {
+                let (dx, dy) = (x as f32 + 0.5 - 16.0, y as f32 + 0.5 - 16.0);
+                let d = (dx * dx + dy * dy).sqrt();
+                let red = g.get_pixel(x, y)[0];
+                if d < 11.0 {
+                    assert_eq!(red, 255);
+                } else if d > 13.0 {
+                    assert_eq!(red, 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_indexed() {
+        use image::draw_many;
+        use Image;
+
+        let data = (0..16 * 16).flat_map(|i| vec![i as u8, 255 - i as u8, 0, 255]).collect();
+        let texture = Texture::from_rgba8(data, 16, 16);
+        let rect = [2.0, 3.0, 10.0, 9.0];
+        let src = [4.0, 2.0, 8.0, 12.0];
+        let mut a = SoftwareGraphics::new(16, 16);
+        a.draw(|c, g| {
+            Image::new().rect(rect).src_rect(src)
+                .draw(&texture, &c.draw_state, c.transform, g)
+        });
+        let mut b = SoftwareGraphics::new(16, 16);
+        b.draw(|c, g| draw_many(&[(rect, src)], [1.0; 4], &texture, &c.draw_state, c.transform, g));
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(a.get_pixel(x, y), b.get_pixel(x, y));
+            }
+        }
+    }
+}
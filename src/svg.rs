@@ -0,0 +1,995 @@
+//! SVG export back-end.
+//!
+//! Shapes with a native SVG counterpart are written as SVG elements,
+//! using the transform as a `matrix(...)`.
+//! Everything else falls back to triangles written as `<path>` elements.
+//!
+//! Scissor rectangles and stencil tests are mapped to `<clipPath>`,
+//! or to `<mask>` for `Stencil::Outside`.
+//! Shapes drawn with `Stencil::Clip` add to the region of their stencil value.
+//!
+//! ```
+//! use graphics::svg::SvgGraphics;
+//! use graphics::{clear, rectangle};
+//!
+//! let mut g = SvgGraphics::new(100, 100);
+//! g.draw(|c, g| {
+//!     clear([1.0; 4], g);
+//!     rectangle([1.0, 0.0, 0.0, 1.0], [10.0, 10.0, 50.0, 50.0], c.transform, g);
+//! });
+//! let svg = g.to_string();
+//! assert!(svg.contains("<rect"));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use texture::{CreateTexture, Format, TextureSettings, UpdateTexture};
+use draw_state::{Blend, Stencil};
+use math::{Matrix2d, Scalar};
+use types::{self, Color};
+use {CircleArc, ComplexPolygon, Context, DrawState, Ellipse, Graphics, Image, ImageSize, Line,
+     Polygon, Polyline, Rectangle, Viewport};
+use polyline::Join;
+use line::Dash;
+use complex_polygon::FillRule;
+use {ellipse, line, rectangle};
+
+/// A texture referenced by an SVG `<image>` element.
+#[derive(Clone)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    href: String,
+    // The pixels of textures created from memory, to update them.
+    rgba: Option<Vec<u8>>,
+}
+
+impl Texture {
+    /// Creates a texture referencing an image by URL.
+    pub fn from_href<S: Into<String>>(href: S, width: u32, height: u32) -> Texture {
+        Texture {
+            width,
+            height,
+            href: href.into(),
+            rgba: None,
+        }
+    }
+
+    /// Gets the URL of the image.
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+}
+
+impl ImageSize for Texture {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Creates a texture embedded as a PNG data URL.
+impl CreateTexture<()> for Texture {
+    type Error = String;
+
+    fn create<S: Into<[u32; 2]>>(_factory: &mut (),
+                                 _format: Format,
+                                 memory: &[u8],
+                                 size: S,
+                                 _settings: &TextureSettings)
+                                 -> Result<Self, Self::Error> {
+        let [w, h] = size.into();
+        if memory.len() < (w * h * 4) as usize {
+            return Err(format!("Expected {} bytes for a {}x{} texture, found {}",
+                               w * h * 4, w, h, memory.len()));
+        }
+        let rgba = memory[..(w * h * 4) as usize].to_vec();
+        Ok(Texture {
+            rgba: Some(rgba),
+            ..Texture::from_href(png_href(memory, w, h), w, h)
+        })
+    }
+}
+
+/// Updates a texture created from memory, encoding it again.
+///
+/// Textures referencing an image by URL can not be updated.
+impl UpdateTexture<()> for Texture {
+    type Error = String;
+
+    fn update<O, S>(&mut self,
+                    _factory: &mut (),
+                    _format: Format,
+                    memory: &[u8],
+                    offset: O,
+                    size: S)
+                    -> Result<(), Self::Error>
+        where O: Into<[u32; 2]>,
+              S: Into<[u32; 2]>
+    {
+        let [x, y] = offset.into();
+        let [w, h] = size.into();
+        let width = self.width;
+        if x as u64 + w as u64 > width as u64 || y as u64 + h as u64 > self.height as u64 {
+            return Err(format!("Update region {}x{} at ({}, {}) is outside {}x{} texture",
+                               w, h, x, y, width, self.height));
+        }
+        let row = w as usize * 4;
+        if memory.len() < row * h as usize {
+            return Err(format!("Expected {} bytes for a {}x{} update, found {}",
+                               row * h as usize, w, h, memory.len()));
+        }
+        let rgba = match self.rgba {
+            Some(ref mut rgba) => rgba,
+            None => return Err("Texture references an image by URL".into()),
+        };
+        if row == 0 || h == 0 {
+            return Ok(());
+        }
+        for (iy, src) in memory.chunks(row).take(h as usize).enumerate() {
+            let dst = (x as usize + (y as usize + iy) * width as usize) * 4;
+            rgba[dst..dst + row].copy_from_slice(src);
+        }
+        self.href = png_href(rgba, width, self.height);
+        Ok(())
+    }
+}
+
+/// Encodes RGBA pixels as a PNG data URL.
+fn png_href(rgba: &[u8], width: u32, height: u32) -> String {
+    format!("data:image/png;base64,{}", base64(&encode_png(rgba, width, height)))
+}
+
+/// A back-end that writes an SVG document.
+///
+/// Use `to_string` to get the document.
+pub struct SvgGraphics {
+    width: u32,
+    height: u32,
+    defs: String,
+    body: String,
+    next_id: usize,
+    scissors: HashMap<[u32; 4], usize>,
+    tints: HashMap<String, usize>,
+    stencil_clear: u8,
+    stencil: HashMap<u8, StencilRegion>,
+}
+
+/// Shapes drawn to the stencil buffer with the same value.
+#[derive(Default)]
+struct StencilRegion {
+    content: String,
+    // The id of the clip path and mask for the current content, if written.
+    clip: Option<usize>,
+    mask: Option<usize>,
+}
+
+impl SvgGraphics {
+    /// Creates a new empty document with size in pixels.
+    pub fn new(width: u32, height: u32) -> SvgGraphics {
+        SvgGraphics {
+            width,
+            height,
+            defs: String::new(),
+            body: String::new(),
+            next_id: 0,
+            scissors: HashMap::new(),
+            tints: HashMap::new(),
+            stencil_clear: 0,
+            stencil: HashMap::new(),
+        }
+    }
+
+    /// Gets a viewport covering the whole document.
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            rect: [0, 0, self.width as i32, self.height as i32],
+            draw_size: [self.width, self.height],
+            window_size: [self.width, self.height],
+        }
+    }
+
+    /// Calls a closure with a context set up for the whole document.
+    pub fn draw<F, U>(&mut self, f: F) -> U
+        where F: FnOnce(Context, &mut Self) -> U
+    {
+        let c = Context::new_viewport(self.viewport());
+        f(c, self)
+    }
+
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Converts from normalized device coordinates to pixels.
+    fn to_pixels(&self, p: [f32; 2]) -> [Scalar; 2] {
+        let (w, h) = (self.width as Scalar, self.height as Scalar);
+        [(p[0] as Scalar + 1.0) * 0.5 * w, (1.0 - p[1] as Scalar) * 0.5 * h]
+    }
+
+    /// Formats a transform to pixels as an SVG `matrix(...)`.
+    fn matrix(&self, m: Matrix2d) -> String {
+        let (w, h) = (0.5 * self.width as Scalar, 0.5 * self.height as Scalar);
+        format!("matrix({} {} {} {} {} {})",
+                num(m[0][0] * w),
+                num(-m[1][0] * h),
+                num(m[0][1] * w),
+                num(-m[1][1] * h),
+                num((m[0][2] + 1.0) * w),
+                num((1.0 - m[1][2]) * h))
+    }
+
+    /// Gets the id of a clip path for a scissor rectangle.
+    fn scissor_id(&mut self, r: [u32; 4]) -> usize {
+        if let Some(&id) = self.scissors.get(&r) {
+            return id;
+        }
+        let id = self.id();
+        self.defs.push_str(&format!("<clipPath id=\"clip{}\"><rect x=\"{}\" y=\"{}\" \
+                                     width=\"{}\" height=\"{}\"/></clipPath>\n",
+                                    id, r[0], r[1], r[2], r[3]));
+        self.scissors.insert(r, id);
+        id
+    }
+
+    /// Gets the id of a filter that multiplies with a color.
+    fn tint_id(&mut self, color: Color) -> usize {
+        let values = format!("{} 0 0 0 0 0 {} 0 0 0 0 0 {} 0 0 0 0 0 {} 0",
+                             num(color[0] as Scalar),
+                             num(color[1] as Scalar),
+                             num(color[2] as Scalar),
+                             num(color[3] as Scalar));
+        if let Some(&id) = self.tints.get(&values) {
+            return id;
+        }
+        let id = self.id();
+        self.defs.push_str(&format!("<filter id=\"tint{}\" \
+                                     color-interpolation-filters=\"sRGB\">\
+                                     <feColorMatrix type=\"matrix\" values=\"{}\"/>\
+                                     </filter>\n",
+                                    id, values));
+        self.tints.insert(values, id);
+        id
+    }
+
+    /// Gets the id of a clip path for the region of a stencil value.
+    fn stencil_clip_id(&mut self, val: u8) -> usize {
+        if let Some(id) = self.stencil.get(&val).and_then(|r| r.clip) {
+            return id;
+        }
+        let id = self.id();
+        // `or_default` needs Rust 1.28.
+        #[allow(clippy::unwrap_or_default)]
+        let region = self.stencil.entry(val).or_insert_with(StencilRegion::default);
+        self.defs.push_str(&format!("<clipPath id=\"clip{}\">{}</clipPath>\n", id, region.content));
+        region.clip = Some(id);
+        id
+    }
+
+    /// Gets the id of a mask hiding the region of a stencil value.
+    fn stencil_mask_id(&mut self, val: u8) -> usize {
+        if let Some(id) = self.stencil.get(&val).and_then(|r| r.mask) {
+            return id;
+        }
+        let id = self.id();
+        let (w, h) = (self.width, self.height);
+        // `or_default` needs Rust 1.28.
+        #[allow(clippy::unwrap_or_default)]
+        let region = self.stencil.entry(val).or_insert_with(StencilRegion::default);
+        self.defs.push_str(&format!("<mask id=\"mask{}\" maskUnits=\"userSpaceOnUse\" \
+                                     x=\"0\" y=\"0\" width=\"{}\" height=\"{}\">\
+                                     <rect width=\"{}\" height=\"{}\" fill=\"white\"/>\
+                                     <g fill=\"black\">{}</g></mask>\n",
+                                    id, w, h, w, h, region.content));
+        region.mask = Some(id);
+        id
+    }
+
+    /// Writes an element, applying the scissor, stencil test and blending.
+    ///
+    /// Elements drawn with `Stencil::Clip` are handled by `tri_list`.
+    fn push(&mut self, draw_state: &DrawState, element: &str) {
+        let mut open = String::new();
+        let mut close = String::new();
+        if let Some(r) = draw_state.scissor {
+            let id = self.scissor_id(r);
+            open.push_str(&format!("<g clip-path=\"url(#clip{})\">", id));
+            close.push_str("</g>");
+        }
+        match draw_state.stencil {
+            Some(Stencil::Inside(val)) => {
+                // Before anything is drawn to the stencil buffer,
+                // the test passes only for the cleared value.
+                let empty = self.stencil.get(&val).map(|r| r.content.is_empty()).unwrap_or(true);
+                if !(empty && val == self.stencil_clear) {
+                    let id = self.stencil_clip_id(val);
+                    open.push_str(&format!("<g clip-path=\"url(#clip{})\">", id));
+                    close.push_str("</g>");
+                }
+            }
+            Some(Stencil::Outside(val)) => {
+                let empty = self.stencil.get(&val).map(|r| r.content.is_empty()).unwrap_or(true);
+                if val == self.stencil_clear && empty {
+                    return;
+                }
+                let id = self.stencil_mask_id(val);
+                open.push_str(&format!("<g mask=\"url(#mask{})\">", id));
+                close.push_str("</g>");
+            }
+            _ => {}
+        }
+        let blend = match draw_state.blend {
+            Some(Blend::Add) => Some("plus-lighter"),
+            Some(Blend::Multiply) => Some("multiply"),
+            Some(Blend::Invert) => Some("difference"),
+            Some(Blend::Alpha) | None => None,
+        };
+        if let Some(mode) = blend {
+            open.push_str(&format!("<g style=\"mix-blend-mode:{}\">", mode));
+            close.push_str("</g>");
+        }
+        self.body.push_str(&open);
+        self.body.push_str(element);
+        self.body.push_str(&close);
+        self.body.push('\n');
+    }
+}
+
+impl fmt::Display for SvgGraphics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,
+                 "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                  xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                  width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+                 w = self.width,
+                 h = self.height)?;
+        if !self.defs.is_empty() {
+            writeln!(f, "<defs>\n{}</defs>", self.defs)?;
+        }
+        write!(f, "{}</svg>", self.body)
+    }
+}
+
+impl Graphics for SvgGraphics {
+    type Texture = Texture;
+
+    fn clear_color(&mut self, color: Color) {
+        self.body.clear();
+        // Nothing refers to the definitions any more.
+        self.defs.clear();
+        self.scissors.clear();
+        self.tints.clear();
+        for region in self.stencil.values_mut() {
+            region.clip = None;
+            region.mask = None;
+        }
+        self.body.push_str(&format!("<rect width=\"{}\" height=\"{}\" {}/>\n",
+                                    self.width,
+                                    self.height,
+                                    paint("fill", color)));
+    }
+
+    fn clear_stencil(&mut self, value: u8) {
+        self.stencil_clear = value;
+        self.stencil.clear();
+    }
+
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]]))
+    {
+        let mut d = String::new();
+        f(&mut |vertices: &[[f32; 2]]| {
+            for tri in vertices.chunks(3).filter(|tri| tri.len() == 3) {
+                let mut p = [self.to_pixels(tri[0]), self.to_pixels(tri[1]), self.to_pixels(tri[2])];
+                // Use the same winding for all triangles,
+                // such that overlapping triangles are filled with the nonzero rule.
+                let cross = (p[1][0] - p[0][0]) * (p[2][1] - p[0][1]) -
+                            (p[1][1] - p[0][1]) * (p[2][0] - p[0][0]);
+                if cross < 0.0 {
+                    p.swap(1, 2);
+                }
+                d.push_str(&format!("M{} {}L{} {}L{} {}Z",
+                                    num(p[0][0]),
+                                    num(p[0][1]),
+                                    num(p[1][0]),
+                                    num(p[1][1]),
+                                    num(p[2][0]),
+                                    num(p[2][1])));
+            }
+        });
+        if d.is_empty() {
+            return;
+        }
+
+        if let Some(Stencil::Clip(val)) = draw_state.stencil {
+            let clip = match draw_state.scissor {
+                Some(r) => format!(" clip-path=\"url(#clip{})\"", self.scissor_id(r)),
+                None => String::new(),
+            };
+            // `or_default` needs Rust 1.28.
+            #[allow(clippy::unwrap_or_default)]
+            let region = self.stencil.entry(val).or_insert_with(StencilRegion::default);
+            region.content.push_str(&format!("<path d=\"{}\"{}/>", d, clip));
+            region.clip = None;
+            region.mask = None;
+        } else {
+            self.push(draw_state, &format!("<path d=\"{}\" {}/>", d, paint("fill", *color)));
+        }
+    }
+
+    fn tri_list_uv<F>(&mut self,
+                      draw_state: &DrawState,
+                      color: &[f32; 4],
+                      texture: &Texture,
+                      mut f: F)
+        where F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]]))
+    {
+        if let Some(Stencil::Clip(_)) = draw_state.stencil {
+            self.tri_list(draw_state, color, |g| {
+                f(&mut |vertices: &[[f32; 2]], _: &[[f32; 2]]| g(vertices))
+            });
+            return;
+        }
+
+        // Each triangle is an image clipped to the triangle,
+        // using the affine transform from texture coordinates to pixels.
+        let (tw, th) = (texture.width as Scalar, texture.height as Scalar);
+        let mut elements = Vec::new();
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            for (tri, uv) in vertices.chunks(3).zip(texture_coords.chunks(3)) {
+                if tri.len() != 3 || uv.len() != 3 {
+                    continue;
+                }
+                let p = [self.to_pixels(tri[0]), self.to_pixels(tri[1]), self.to_pixels(tri[2])];
+                let t = [[uv[0][0] as Scalar * tw, uv[0][1] as Scalar * th],
+                         [uv[1][0] as Scalar * tw, uv[1][1] as Scalar * th],
+                         [uv[2][0] as Scalar * tw, uv[2][1] as Scalar * th]];
+                if let Some(m) = affine(t, p) {
+                    elements.push((p, m));
+                }
+            }
+        });
+
+        let filter = if *color == [1.0; 4] {
+            String::new()
+        } else {
+            format!(" filter=\"url(#tint{})\"", self.tint_id(*color))
+        };
+        for (p, m) in elements {
+            let id = self.id();
+            self.defs.push_str(&format!("<clipPath id=\"clip{}\"><path d=\"M{} {}L{} {}L{} {}Z\"/>\
+                                         </clipPath>\n",
+                                        id,
+                                        num(p[0][0]),
+                                        num(p[0][1]),
+                                        num(p[1][0]),
+                                        num(p[1][1]),
+                                        num(p[2][0]),
+                                        num(p[2][1])));
+            let element = format!("<g clip-path=\"url(#clip{})\"{}><image transform=\"matrix({} {} \
+                                   {} {} {} {})\" width=\"{}\" height=\"{}\" \
+                                   preserveAspectRatio=\"none\" xlink:href=\"{}\"/></g>",
+                                  id,
+                                  filter,
+                                  num(m[0][0]),
+                                  num(m[1][0]),
+                                  num(m[0][1]),
+                                  num(m[1][1]),
+                                  num(m[0][2]),
+                                  num(m[1][2]),
+                                  texture.width,
+                                  texture.height,
+                                  texture.href);
+            self.push(draw_state, &element);
+        }
+    }
+
+    fn rectangle<R: Into<types::Rectangle>>(&mut self,
+                                            r: &Rectangle,
+                                            rectangle: R,
+                                            draw_state: &DrawState,
+                                            transform: Matrix2d) {
+        if is_clip(draw_state) {
+            r.draw_tri(rectangle, draw_state, transform, self);
+            return;
+        }
+
+        let rect = rectangle.into();
+        let shape = |attrs: String| -> String {
+            let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
+            match r.shape {
+                rectangle::Shape::Square => {
+                    format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                            num(x), num(y), num(w), num(h), attrs)
+                }
+                rectangle::Shape::Round(radius, _) => {
+                    format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {}/>",
+                            num(x), num(y), num(w), num(h), num(radius), attrs)
+                }
+                rectangle::Shape::Bevel(b) => {
+                    let points = [[x + b, y], [x + w - b, y], [x + w, y + b], [x + w, y + h - b],
+                                  [x + w - b, y + h], [x + b, y + h], [x, y + h - b], [x, y + b]];
+                    format!("<polygon points=\"{}\" {}/>", points_attr(&points), attrs)
+                }
+            }
+        };
+        let matrix = self.matrix(transform);
+        if r.color[3] != 0.0 {
+            let element = shape(format!("transform=\"{}\" {}", matrix, paint("fill", r.color)));
+            self.push(draw_state, &element);
+        }
+        if let Some(rectangle::Border { color, radius, dash }) = r.border {
+            if color[3] != 0.0 {
+                let element = shape(format!("transform=\"{}\" fill=\"none\" {} \
+                                             stroke-width=\"{}\"{}",
+                                            matrix,
+                                            paint("stroke", color),
+                                            num(2.0 * radius),
+                                            dash_attrs(dash)));
+                self.push(draw_state, &element);
+            }
+        }
+    }
+
+    fn polygon(&mut self,
+               p: &Polygon,
+               polygon: types::Polygon,
+               draw_state: &DrawState,
+               transform: Matrix2d) {
+        if is_clip(draw_state) {
+            p.draw_tri(polygon, draw_state, transform, self);
+            return;
+        }
+
+        let element = format!("<polygon points=\"{}\" transform=\"{}\" {}/>",
+                              points_attr(polygon),
+                              self.matrix(transform),
+                              paint("fill", p.color));
+        self.push(draw_state, &element);
+    }
+
+    fn complex_polygon(&mut self,
+                       p: &ComplexPolygon,
+                       contours: types::Polygons,
+                       draw_state: &DrawState,
+                       transform: Matrix2d) {
+        if is_clip(draw_state) {
+            p.draw_tri(contours, draw_state, transform, self);
+            return;
+        }
+
+        let mut d = String::new();
+        for contour in contours.iter().filter(|c| c.len() >= 3) {
+            d.push_str(&format!("M{}Z", points_attr(contour).replace(' ', "L")));
+        }
+        let fill_rule = match p.fill_rule {
+            FillRule::NonZero => "nonzero",
+            FillRule::EvenOdd => "evenodd",
+        };
+        let element = format!("<path d=\"{}\" fill-rule=\"{}\" transform=\"{}\" {}/>",
+                              d,
+                              fill_rule,
+                              self.matrix(transform),
+                              paint("fill", p.color));
+        self.push(draw_state, &element);
+    }
+
+    fn image(&mut self,
+             image: &Image,
+             texture: &Texture,
+             draw_state: &DrawState,
+             transform: Matrix2d) {
+        if is_clip(draw_state) {
+            image.draw_tri(texture, draw_state, transform, self);
+            return;
+        }
+
+        let (tw, th) = texture.get_size();
+        let src = image.source_rectangle.unwrap_or([0.0, 0.0, tw as Scalar, th as Scalar]);
+        let rect = image.rectangle.unwrap_or([0.0, 0.0, src[2], src[3]]);
+        let color = image.color.unwrap_or([1.0; 4]);
+        let filter = if color == [1.0; 4] {
+            String::new()
+        } else {
+            format!(" filter=\"url(#tint{})\"", self.tint_id(color))
+        };
+        // A nested `<svg>` element clips the image to the source rectangle.
+        let element = format!("<g transform=\"{}\"{}><svg x=\"{}\" y=\"{}\" width=\"{}\" \
+                               height=\"{}\" viewBox=\"{} {} {} {}\" \
+                               preserveAspectRatio=\"none\"><image width=\"{}\" height=\"{}\" \
+                               xlink:href=\"{}\"/></svg></g>",
+                              self.matrix(transform),
+                              filter,
+                              num(rect[0]),
+                              num(rect[1]),
+                              num(rect[2]),
+                              num(rect[3]),
+                              num(src[0]),
+                              num(src[1]),
+                              num(src[2]),
+                              num(src[3]),
+                              tw,
+                              th,
+                              texture.href);
+        self.push(draw_state, &element);
+    }
+
+    fn ellipse<R: Into<types::Rectangle>>(&mut self,
+                                          e: &Ellipse,
+                                          rectangle: R,
+                                          draw_state: &DrawState,
+                                          transform: Matrix2d) {
+        if is_clip(draw_state) {
+            e.draw_tri(rectangle, draw_state, transform, self);
+            return;
+        }
+
+        let rect = rectangle.into();
+        let (rx, ry) = (0.5 * rect[2], 0.5 * rect[3]);
+        let geometry = format!("<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" transform=\"{}\"",
+                               num(rect[0] + rx),
+                               num(rect[1] + ry),
+                               num(rx),
+                               num(ry),
+                               self.matrix(transform));
+        if e.color[3] != 0.0 {
+            let element = format!("{} {}/>", geometry, paint("fill", e.color));
+            self.push(draw_state, &element);
+        }
+        if let Some(ellipse::Border { color, radius, dash }) = e.border {
+            if color[3] != 0.0 {
+                let element = format!("{} fill=\"none\" {} stroke-width=\"{}\"{}/>",
+                                      geometry,
+                                      paint("stroke", color),
+                                      num(2.0 * radius),
+                                      dash_attrs(dash));
+                self.push(draw_state, &element);
+            }
+        }
+    }
+
+    fn line<L: Into<types::Line>>(&mut self,
+                                  l: &Line,
+                                  line: L,
+                                  draw_state: &DrawState,
+                                  transform: Matrix2d) {
+        let cap = match l.shape {
+            line::Shape::Square => "butt",
+            line::Shape::Round => "round",
+            line::Shape::Bevel => "",
+        };
+        if is_clip(draw_state) || cap.is_empty() {
+            l.draw_tri(line, draw_state, transform, self);
+            return;
+        }
+
+        let line = line.into();
+        let element = format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" transform=\"{}\" {} \
+                               stroke-width=\"{}\" stroke-linecap=\"{}\"{}/>",
+                              num(line[0]),
+                              num(line[1]),
+                              num(line[2]),
+                              num(line[3]),
+                              self.matrix(transform),
+                              paint("stroke", l.color),
+                              num(2.0 * l.radius),
+                              cap,
+                              dash_attrs(l.dash));
+        self.push(draw_state, &element);
+    }
+
+    fn polyline(&mut self,
+                p: &Polyline,
+                points: types::Polygon,
+                draw_state: &DrawState,
+                transform: Matrix2d) {
+        let cap = match p.shape {
+            line::Shape::Square => "butt",
+            line::Shape::Round => "round",
+            line::Shape::Bevel => "",
+        };
+        if is_clip(draw_state) || cap.is_empty() {
+            p.draw_tri(points, draw_state, transform, self);
+            return;
+        }
+
+        let join = match p.join {
+            Join::Miter(limit) => format!("miter\" stroke-miterlimit=\"{}", num(limit.max(1.0))),
+            Join::Round => "round".into(),
+            Join::Bevel => "bevel".into(),
+        };
+        let element = format!("<{} points=\"{}\" transform=\"{}\" fill=\"none\" {} \
+                               stroke-width=\"{}\" stroke-linecap=\"{}\" stroke-linejoin=\"{}\"/>",
+                              if p.closed { "polygon" } else { "polyline" },
+                              points_attr(points),
+                              self.matrix(transform),
+                              paint("stroke", p.color),
+                              num(2.0 * p.radius),
+                              cap,
+                              join);
+        self.push(draw_state, &element);
+    }
+
+    fn circle_arc<R: Into<types::Rectangle>>(&mut self,
+                                             c: &CircleArc,
+                                             rectangle: R,
+                                             draw_state: &DrawState,
+                                             transform: Matrix2d) {
+        use radians::Radians;
+
+        if is_clip(draw_state) {
+            c.draw_tri(rectangle, draw_state, transform, self);
+            return;
+        }
+
+        let rect = rectangle.into();
+        let twopi = <Scalar as Radians>::_360();
+        let delta = (((c.end - c.start) % twopi) + twopi) % twopi;
+        if delta == 0.0 {
+            return;
+        }
+        let (rx, ry) = (0.5 * rect[2], 0.5 * rect[3]);
+        let (cx, cy) = (rect[0] + rx, rect[1] + ry);
+        let end = c.start + delta;
+        let element = format!("<path d=\"M{} {}A{} {} 0 {} 1 {} {}\" transform=\"{}\" \
+                               fill=\"none\" {} stroke-width=\"{}\"/>",
+                              num(cx + c.start.cos() * rx),
+                              num(cy + c.start.sin() * ry),
+                              num(rx),
+                              num(ry),
+                              if delta > <Scalar as Radians>::_180() { 1 } else { 0 },
+                              num(cx + end.cos() * rx),
+                              num(cy + end.sin() * ry),
+                              self.matrix(transform),
+                              paint("stroke", c.color),
+                              num(2.0 * c.radius));
+        self.push(draw_state, &element);
+    }
+}
+
+// `matches!` needs Rust 1.42.
+#[allow(clippy::match_like_matches_macro)]
+fn is_clip(draw_state: &DrawState) -> bool {
+    match draw_state.stencil {
+        Some(Stencil::Clip(_)) => true,
+        _ => false,
+    }
+}
+
+/// Formats a number with limited precision.
+fn num(x: Scalar) -> String {
+    let s = format!("{:.3}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+/// Formats the points of a polygon for the `points` attribute.
+fn points_attr(points: &[[Scalar; 2]]) -> String {
+    let points: Vec<String> = points.iter()
+        .map(|p| format!("{},{}", num(p[0]), num(p[1])))
+        .collect();
+    points.join(" ")
+}
+
+/// Formats a color as a fill or stroke attribute.
+fn paint(attr: &str, color: Color) -> String {
+    // `clamp` needs Rust 1.50.
+    #[allow(clippy::manual_clamp)]
+    let c = |v: f32| (v.max(0.0).min(1.0) * 255.0).round() as u8;
+    let mut res = format!("{}=\"#{:02x}{:02x}{:02x}\"", attr, c(color[0]), c(color[1]), c(color[2]));
+    if color[3] < 1.0 {
+        res.push_str(&format!(" {}-opacity=\"{}\"", attr, num(color[3] as Scalar)));
+    }
+    res
+}
+
+/// Formats a dash pattern as stroke attributes.
+fn dash_attrs(dash: Option<Dash>) -> String {
+    match dash {
+        Some(dash) => {
+            let lengths: Vec<String> = dash.lengths().iter().map(|&x| num(x)).collect();
+            format!(" stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"",
+                    lengths.join(" "),
+                    num(dash.offset))
+        }
+        None => String::new(),
+    }
+}
+
+/// Computes the affine transform mapping triangle `a` to triangle `b`.
+fn affine(a: [[Scalar; 2]; 3], b: [[Scalar; 2]; 3]) -> Option<Matrix2d> {
+    let (ax, ay) = (a[1][0] - a[0][0], a[1][1] - a[0][1]);
+    let (bx, by) = (a[2][0] - a[0][0], a[2][1] - a[0][1]);
+    let det = ax * by - ay * bx;
+    if det == 0.0 {
+        return None;
+    }
+    let mut m = [[0.0; 3]; 2];
+    for i in 0..2 {
+        let (d1, d2) = (b[1][i] - b[0][i], b[2][i] - b[0][i]);
+        m[i][0] = (d1 * by - d2 * ay) / det;
+        m[i][1] = (d2 * ax - d1 * bx) / det;
+        m[i][2] = b[0][i] - m[i][0] * a[0][0] - m[i][1] * a[0][1];
+    }
+    Some(m)
+}
+
+/// Encodes RGBA8 pixels as an uncompressed PNG image.
+fn encode_png(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    // Each row starts with filter type 0.
+    let row = (width * 4) as usize;
+    let mut raw = Vec::with_capacity((row + 1) * height as usize);
+    for y in 0..height as usize {
+        raw.push(0);
+        raw.extend_from_slice(&rgba[y * row..(y + 1) * row]);
+    }
+
+    // Zlib stream with stored deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if raw.is_empty() { vec![&[]] } else { raw.chunks(0xffff).collect() };
+    for (i, block) in blocks.iter().enumerate() {
+        zlib.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, default compression, filter and interlacing.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = String::with_capacity(data.len() / 3 * 4 + 4);
+    for c in data.chunks(3) {
+        let n = (c[0] as u32) << 16 | (*c.get(1).unwrap_or(&0) as u32) << 8 |
+                *c.get(2).unwrap_or(&0) as u32;
+        res.push(CHARS[(n >> 18) as usize & 63] as char);
+        res.push(CHARS[(n >> 12) as usize & 63] as char);
+        res.push(if c.len() > 1 { CHARS[(n >> 6) as usize & 63] as char } else { '=' });
+        res.push(if c.len() > 2 { CHARS[n as usize & 63] as char } else { '=' });
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {clear, Transformed};
+
+    #[test]
+    fn test_matrix() {
+        let g = SvgGraphics::new(200, 100);
+        let c = Context::new_viewport(g.viewport());
+        assert_eq!(g.matrix(c.transform), "matrix(1 0 0 1 0 0)");
+        assert_eq!(g.matrix(c.transform.trans(10.0, 5.0)), "matrix(1 0 0 1 10 5)");
+    }
+
+    #[test]
+    fn test_clipping() {
+        let mut g = SvgGraphics::new(100, 100);
+        g.draw(|c, g| {
+            clear([1.0; 4], g);
+            let rect = Rectangle::new([1.0, 0.0, 0.0, 1.0]);
+            rect.draw([0.0, 0.0, 50.0, 50.0], &DrawState::new_clip(), c.transform, g);
+            rect.draw([0.0, 0.0, 100.0, 100.0], &DrawState::new_inside(), c.transform, g);
+            rect.draw([0.0, 0.0, 100.0, 100.0],
+                      &c.draw_state.scissor([0, 0, 10, 10]),
+                      c.transform,
+                      g);
+        });
+        let svg = g.to_string();
+        assert!(svg.contains("<clipPath id=\"clip1\"><path d=\"M0 0L50 0L0 50Z"));
+        assert!(svg.contains("<g clip-path=\"url(#clip1)\"><rect"));
+        assert!(svg.contains("<clipPath id=\"clip2\"><rect x=\"0\" y=\"0\" width=\"10\" \
+                              height=\"10\"/></clipPath>"));
+
+        // Clearing removes the definitions with the shapes using them.
+        g.draw(|c, g| {
+            clear([1.0; 4], g);
+            Rectangle::new([1.0; 4]).draw([0.0, 0.0, 10.0, 10.0], &DrawState::new_inside(), c.transform, g);
+        });
+        let svg = g.to_string();
+        assert!(!svg.contains("clip1") && !svg.contains("clip2"));
+        assert!(svg.contains("<clipPath id=\"clip3\"></clipPath>"));
+    }
+
+    #[test]
+    fn test_complex_polygon() {
+        let mut g = SvgGraphics::new(100, 100);
+        g.draw(|c, g| {
+            let outer = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]];
+            let inner = [[5.0, 2.0], [8.0, 2.0], [8.0, 5.0]];
+            ComplexPolygon::new([0.0, 0.0, 1.0, 1.0])
+                .draw(&[&outer, &inner], &c.draw_state, c.transform, g);
+        });
+        let svg = g.to_string();
+        assert!(svg.contains("<path d=\"M0,0L10,0L10,10ZM5,2L8,2L8,5Z\" fill-rule=\"evenodd\""));
+    }
+
+    #[test]
+    fn test_transparent() {
+        let mut g = SvgGraphics::new(100, 100);
+        g.draw(|c, g| {
+            let rect = [0.0, 0.0, 10.0, 10.0];
+            Ellipse::new_border([1.0; 4], 1.0).draw(rect, &c.draw_state, c.transform, g);
+            Ellipse::new([1.0; 4]).border(ellipse::Border {
+                color: [0.0; 4],
+                radius: 1.0,
+                dash: None,
+            }).draw(rect, &c.draw_state, c.transform, g);
+        });
+        let svg = g.to_string();
+        assert_eq!(svg.matches("<ellipse").count(), 2);
+        assert!(svg.contains("fill=\"none\""));
+    }
+
+    #[test]
+    fn test_affine() {
+        let a = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let b = [[10.0, 20.0], [12.0, 20.0], [10.0, 23.0]];
+        assert_eq!(affine(a, b), Some([[2.0, 0.0, 10.0], [0.0, 3.0, 20.0]]));
+    }
+
+    #[test]
+    fn test_update_texture() {
+        let settings = TextureSettings::new();
+        let mut texture = Texture::create(&mut (), Format::Rgba8, &[0; 16], [2, 2], &settings).unwrap();
+        texture.update(&mut (), Format::Rgba8, &[255; 4], [1, 1], [1, 1]).unwrap();
+        let mut rgba = vec![0; 16];
+        rgba[12..].copy_from_slice(&[255; 4]);
+        assert_eq!(texture.href(), png_href(&rgba, 2, 2));
+        assert!(texture.update(&mut (), Format::Rgba8, &[255; 8], [1, 1], [2, 1]).is_err());
+        assert!(Texture::from_href("a.png", 2, 2)
+            .update(&mut (), Format::Rgba8, &[255; 4], [0, 0], [1, 1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+}
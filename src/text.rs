@@ -1,9 +1,44 @@
 //! Draw text
 
-use types::{Color, FontSize};
-use {color, Image, Graphics, Transformed, DrawState};
-use character::CharacterCache;
-use math::Matrix2d;
+use types::{Color, FontSize, Rectangle, SourceRectangle};
+use {color, image, triangulation, Graphics, DrawState, ImageSize, Transformed};
+use character::{Character, CharacterCache, FontMetrics};
+use rectangle;
+use text_layout::Layout;
+use text_path::TextPath;
+use math::{Matrix2d, Scalar, Vec2d};
+
+/// An outline around the characters of text
+///
+/// With a distance field character cache, the outline is drawn by growing the characters,
+/// up to the spread of the distance field.
+/// Otherwise it is drawn by stamping the characters around their position,
+/// so translucent colors get darker where the stamps overlap.
+/// The stamps of a character are drawn with a single call to the back-end,
+/// but each character is drawn again about `π·(width/step)²` times (at least 8),
+/// and as often again with a shadow, so keep the width small.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Outline {
+    /// The color
+    pub color: Color,
+    /// The width in the text's local units, so it scales with the transform
+    pub width: Scalar,
+    /// The distance between stamps in the text's local units
+    ///
+    /// Use a step below 1 for text scaled up by the transform,
+    /// so the stamps do not leave gaps.
+    /// The step is at least a sixteenth of the width.
+    pub step: Scalar,
+}
+
+/// A shadow behind text
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Shadow {
+    /// The color
+    pub color: Color,
+    /// The offset from the text
+    pub offset: Vec2d,
+}
 
 /// Renders text
 #[derive(Copy, Clone)]
@@ -14,16 +49,20 @@ pub struct Text {
     pub font_size: FontSize,
     /// Whether or not the text's position should be rounded (to a signed distance field).
     pub round: bool,
+    /// Whether to draw a line below the text
+    pub underline: bool,
+    /// Whether to draw a line through the text
+    pub strikethrough: bool,
+    /// The outline around the characters and lines, if any
+    pub outline: Option<Outline>,
+    /// The shadow behind the text, if any
+    pub shadow: Option<Shadow>,
 }
 
 impl Text {
     /// Creates a new text with black color
     pub fn new(font_size: FontSize) -> Text {
-        Text {
-            color: color::BLACK,
-            font_size: font_size,
-            round: false,
-        }
+        Text::new_color(color::BLACK, font_size)
     }
 
     /// Creates a new colored text
@@ -32,6 +71,10 @@ impl Text {
             color: color,
             font_size: font_size,
             round: false,
+            underline: false,
+            strikethrough: false,
+            outline: None,
+            shadow: None,
         }
     }
 
@@ -41,6 +84,42 @@ impl Text {
         self
     }
 
+    /// A builder method drawing a line below the text.
+    pub fn underline(mut self) -> Text {
+        self.underline = true;
+        self
+    }
+
+    /// A builder method drawing a line through the text.
+    pub fn strikethrough(mut self) -> Text {
+        self.strikethrough = true;
+        self
+    }
+
+    /// A builder method drawing an outline around the characters and lines.
+    pub fn outline(mut self, color: Color, width: Scalar) -> Text {
+        self.outline = Some(Outline { color, width, step: 1.0 });
+        self
+    }
+
+    /// A builder method setting the distance between the stamps of the outline.
+    ///
+    /// Call it after `outline`.
+    pub fn outline_step(mut self, step: Scalar) -> Text {
+        if let Some(ref mut outline) = self.outline {
+            outline.step = step;
+        }
+        self
+    }
+
+    /// A builder method drawing a shadow behind the text.
+    ///
+    /// The shadow includes the outline.
+    pub fn shadow(mut self, color: Color, offset: Vec2d) -> Text {
+        self.shadow = Some(Shadow { color, offset });
+        self
+    }
+
     /// Draws text with a character cache
     pub fn draw<C, G>(&self,
                       text: &str,
@@ -52,24 +131,482 @@ impl Text {
         where C: CharacterCache,
               G: Graphics<Texture = <C as CharacterCache>::Texture>
     {
-        let image = Image::new_color(self.color);
+        let mut glyphs = Vec::with_capacity(text.len());
         let mut x = 0.0;
         let mut y = 0.0;
+        let mut prev = None;
         for ch in text.chars() {
-            let character = cache.character(self.font_size, ch)?;
-            let mut ch_x = x + character.left();
-            let mut ch_y = y - character.top();
-            if self.round {
-                ch_x = ch_x.round();
-                ch_y = ch_y.round();
+            if let Some(prev) = prev {
+                x += cache.kerning(self.font_size, prev, ch)?;
             }
-            image.draw(character.texture,
-                       draw_state,
-                       transform.trans(ch_x, ch_y),
-                       g);
+            prev = Some(ch);
+            let character = cache.subpixel_character(self.font_size, ch, x)?;
+            glyphs.push(self.place(ch, [x, y], &character));
             x += character.width();
             y += character.height();
         }
+        self.draw_decorated(&glyphs, &[[0.0, x, 0.0]], cache, draw_state, transform, g)
+    }
+
+    /// Draws text broken into lines by `TextLayout`.
+    ///
+    /// The layout should use the same font size as the text.
+    pub fn draw_layout<C, G>(&self,
+                             layout: &Layout,
+                             cache: &mut C,
+                             draw_state: &DrawState,
+                             transform: Matrix2d,
+                             g: &mut G)
+                             -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let glyphs: Vec<(char, Vec2d)> = layout.glyphs.iter()
+            .filter(|glyph| !glyph.ch.is_whitespace())
+            .map(|glyph| (glyph.ch, glyph.position))
+            .collect();
+        let glyphs = self.place_all(&glyphs, cache)?;
+        let lines: Vec<[Scalar; 3]> = layout.lines.iter()
+            .map(|line| [line.x, line.x + line.width, line.baseline])
+            .collect();
+        self.draw_decorated(&glyphs, &lines, cache, draw_state, transform, g)
+    }
+
+    /// Draws text along a path, with the baseline on the path.
+    ///
+    /// Every character is rotated to the direction of the path at its middle.
+    /// Underlines and strikethroughs are not drawn along paths.
+    pub fn draw_path<C, G>(&self,
+                           text: &str,
+                           path: &TextPath,
+                           cache: &mut C,
+                           draw_state: &DrawState,
+                           transform: Matrix2d,
+                           g: &mut G)
+                           -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let mut glyphs = vec![];
+        for glyph in path.place(text, self.font_size, cache)? {
+            if !glyph.ch.is_whitespace() {
+                let placed = self.place_all(&[(glyph.ch, [-0.5 * glyph.advance, 0.0])], cache)?;
+                glyphs.push((glyph, placed));
+            }
+        }
+        for (color, outline, offset) in self.layers() {
+            let layer = Text { color, outline, ..*self };
+            for (glyph, placed) in &glyphs {
+                let transform = transform.trans(offset[0], offset[1])
+                    .trans(glyph.position[0], glyph.position[1])
+                    .orient(glyph.direction[0], glyph.direction[1]);
+                layer.draw_glyphs(placed, cache, draw_state, transform, g)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the color, outline and offset of the shadow, outline and text,
+    /// in the order they are drawn.
+    fn layers(&self) -> Vec<(Color, Option<Outline>, Vec2d)> {
+        let mut layers = vec![];
+        if let Some(shadow) = self.shadow {
+            layers.push((shadow.color, self.outline, shadow.offset));
+        }
+        if let Some(outline) = self.outline {
+            layers.push((outline.color, self.outline, [0.0; 2]));
+        }
+        layers.push((self.color, None, [0.0; 2]));
+        layers
+    }
+
+    /// Returns the vertical center and thickness of the lines below and through the text,
+    /// relative to the baseline.
+    fn decorations(&self, metrics: &FontMetrics) -> Vec<(Scalar, Scalar)> {
+        let thickness = (metrics.height() / 14.0).max(1.0);
+        let mut lines = vec![];
+        if self.underline {
+            lines.push((-0.5 * metrics.descent, thickness));
+        }
+        if self.strikethrough {
+            lines.push((-0.3 * metrics.ascent, thickness));
+        }
+        lines
+    }
+
+    /// Draws characters at pen positions, with shadow, outline and decorations.
+    ///
+    /// Decorations are drawn along lines of `[start x, end x, baseline]`.
+    fn draw_decorated<C, G>(&self,
+                            glyphs: &[PlacedGlyph],
+                            lines: &[[Scalar; 3]],
+                            cache: &mut C,
+                            draw_state: &DrawState,
+                            transform: Matrix2d,
+                            g: &mut G)
+                            -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let decorations = if self.underline || self.strikethrough {
+            self.decorations(&cache.metrics(self.font_size)?)
+        } else {
+            vec![]
+        };
+        for (color, outline, offset) in self.layers() {
+            let layer = Text { color, outline, ..*self };
+            let transform = transform.trans(offset[0], offset[1]);
+            layer.draw_glyphs(glyphs, cache, draw_state, transform, g)?;
+            let width = outline.map(|outline| outline.width).unwrap_or(0.0);
+            for line in lines {
+                for &(y, thickness) in &decorations {
+                    let rect = [line[0] - width,
+                                line[2] + y - 0.5 * thickness - width,
+                                line[1] - line[0] + 2.0 * width,
+                                thickness + 2.0 * width];
+                    rectangle::Rectangle::new(color).draw(rect, draw_state, transform, g);
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Places a character looked up for a pen position.
+    fn place<T: ImageSize>(&self, ch: char, pos: Vec2d, character: &Character<T>) -> PlacedGlyph {
+        let mut ch_x = pos[0] + character.left();
+        let mut ch_y = pos[1] - character.top();
+        if self.round {
+            ch_x = ch_x.round();
+            ch_y = ch_y.round();
+        }
+        let [w, h] = character.image_size;
+        let [src_w, src_h] = character.atlas_size;
+        PlacedGlyph {
+            ch,
+            x: pos[0],
+            texture_id: character.texture_id,
+            rect: [ch_x, ch_y, w, h],
+            src_rect: [character.atlas_offset[0], character.atlas_offset[1], src_w, src_h],
+        }
+    }
+
+    /// Looks up and places characters at pen positions.
+    ///
+    /// All characters are looked up before drawing,
+    /// so the cache does not change while the batches are drawn,
+    /// unless it can not hold all the characters.
+    /// Caches with subpixel positioning provide the glyph for each pen position.
+    fn place_all<C>(&self, glyphs: &[(char, Vec2d)], cache: &mut C)
+                    -> Result<Vec<PlacedGlyph>, C::Error>
+        where C: CharacterCache
+    {
+        let mut placed = Vec::with_capacity(glyphs.len());
+        for &(ch, pos) in glyphs {
+            let character = cache.subpixel_character(self.font_size, ch, pos[0])?;
+            placed.push(self.place(ch, pos, &character));
+        }
+        Ok(placed)
+    }
+
+    /// Draws placed characters.
+    ///
+    /// Characters next to each other with the same texture id,
+    /// such as glyphs in an atlas, are drawn with a single call to the back-end.
+    fn draw_glyphs<C, G>(&self,
+                         glyphs: &[PlacedGlyph],
+                         cache: &mut C,
+                         draw_state: &DrawState,
+                         transform: Matrix2d,
+                         g: &mut G)
+                         -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let mut start = 0;
+        for i in 1..glyphs.len() + 1 {
+            let id = glyphs[start].texture_id;
+            if i == glyphs.len() || id.is_none() || glyphs[i].texture_id != id {
+                self.draw_batch(&glyphs[start..i], cache, draw_state, transform, g)?;
+                start = i;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws a batch of characters sharing a texture, with their outline if any,
+    /// looking up the texture with the first character.
+    ///
+    /// Distance fields are drawn with `Graphics::tri_list_uv_sdf`,
+    /// with a threshold that grows the characters to the outline.
+    /// Other characters are stamped around their position for the outline.
+    fn draw_batch<C, G>(&self,
+                        batch: &[PlacedGlyph],
+                        cache: &mut C,
+                        draw_state: &DrawState,
+                        transform: Matrix2d,
+                        g: &mut G)
+                        -> Result<(), C::Error>
+        where C: CharacterCache,
+              G: Graphics<Texture = <C as CharacterCache>::Texture>
+    {
+        let distance_field = cache.is_distance_field();
+        let spread = cache.distance_field_spread();
+        let texture = cache.subpixel_character(self.font_size, batch[0].ch, batch[0].x)?.texture;
+        if distance_field {
+            let rects: Vec<(Rectangle, SourceRectangle)> = batch.iter()
+                .map(|glyph| (glyph.rect, glyph.src_rect))
+                .collect();
+            let threshold = match self.outline {
+                Some(outline) if outline.width > 0.0 => {
+                    // The texels of the field per local unit.
+                    let scale = batch.iter()
+                        .find(|glyph| glyph.rect[2] > 0.0)
+                        .map(|glyph| glyph.src_rect[2] / glyph.rect[2])
+                        .unwrap_or(0.0);
+                    (0.5 - outline.width * scale / (2.0 * spread)).max(0.0)
+                }
+                _ => 0.5,
+            };
+            draw_many_sdf(&rects, self.color, texture, threshold as f32, draw_state, transform, g);
+        } else {
+            let offsets = match self.outline {
+                Some(outline) => outline_offsets(outline.width, outline.step),
+                None => vec![[0.0; 2]],
+            };
+            let mut rects: Vec<(Rectangle, SourceRectangle)> =
+                Vec::with_capacity(offsets.len() * batch.len());
+            for d in &offsets {
+                rects.extend(batch.iter().map(|glyph| {
+                    let r = glyph.rect;
+                    ([r[0] + d[0], r[1] + d[1], r[2], r[3]], glyph.src_rect)
+                }));
+            }
+            image::draw_many(&rects, self.color, texture, draw_state, transform, g);
+        }
+        Ok(())
+    }
+}
+
+/// A character with the rectangles to draw it with
+#[derive(Copy, Clone, Debug)]
+struct PlacedGlyph {
+    /// The character, to look up its texture when drawing.
+    ch: char,
+    /// The pen position the character was looked up for.
+    x: Scalar,
+    /// The id of the texture, to batch characters with.
+    texture_id: Option<usize>,
+    /// The rectangle to draw the character image in.
+    rect: Rectangle,
+    /// The rectangle of the character in the texture.
+    src_rect: SourceRectangle,
+}
+
+/// Returns the offsets to draw characters at for an outline of a width.
+///
+/// The offsets are on rings `step` apart, with points about `step` apart,
+/// so the characters cover the outline without gaps.
+/// The step is at least a sixteenth of the width, to limit the number of offsets.
+fn outline_offsets(width: Scalar, step: Scalar) -> Vec<Vec2d> {
+    use std::f64::consts::PI;
+
+    if width <= 0.0 {
+        return vec![[0.0; 2]];
+    }
+    let step = step.max(width / 16.0);
+    let mut offsets = vec![];
+    let mut radius = width;
+    while radius > 0.0 {
+        let n = ((2.0 * PI * radius / step).ceil() as usize).max(8);
+        offsets.extend((0..n).map(|i| {
+            let angle = 2.0 * PI * i as Scalar / n as Scalar;
+            [radius * angle.cos(), radius * angle.sin()]
+        }));
+        radius -= step;
+    }
+    offsets
+}
+
+/// Draws rectangles of a distance field texture.
+fn draw_many_sdf<G>(rects: &[(Rectangle, SourceRectangle)],
+                    color: Color,
+                    texture: &<G as Graphics>::Texture,
+                    threshold: f32,
+                    draw_state: &DrawState,
+                    transform: Matrix2d,
+                    g: &mut G)
+    where G: Graphics
+{
+    use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+
+    let mut vertices: Vec<[f32; 2]> = Vec::with_capacity(BUFFER_SIZE);
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(BUFFER_SIZE);
+    g.tri_list_uv_sdf(draw_state, &color, texture, threshold, |f| for chunk in rects.chunks(BUFFER_SIZE / 6) {
+        vertices.clear();
+        uvs.clear();
+        for r in chunk {
+            vertices.extend_from_slice(&triangulation::rect_tri_list_xy(transform, r.0));
+            uvs.extend_from_slice(&triangulation::rect_tri_list_uv(texture, r.1));
+        }
+        f(&vertices, &uvs)
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mock::Font;
+    use recorder::{Command, Recorder, Texture};
+    use Context;
+
+    /// Lower case and upper case characters in two atlas textures.
+    fn atlas() -> Font {
+        Font::new(Texture::new(0, 64, 8)).upper_case(Texture::new(1, 64, 8))
+    }
+
+    #[test]
+    fn test_batches() {
+        let c = Context::new_abs(64.0, 64.0);
+        let mut cache = atlas();
+        let mut recorder = Recorder::new();
+        Text::new(10).draw("abcDEf", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        let textures: Vec<(usize, usize)> = recorder.commands.iter().map(|command| match *command {
+            Command::TriListUvIndexed { texture, ref chunks, .. } => {
+                (texture.id, chunks[0].indices.len() / 6)
+            }
+            _ => panic!("expected indexed triangles"),
+        }).collect();
+        assert_eq!(textures, vec![(0, 3), (1, 2), (0, 1)]);
+        // Each glyph is looked up once, and each batch once more for its texture.
+        assert_eq!(cache.lookups, 6 + 3);
+    }
+
+    #[test]
+    fn test_no_texture_id() {
+        let c = Context::new_abs(64.0, 64.0);
+        let mut cache = Font::new(Texture::new(0, 8, 8)).whole();
+        let mut recorder = Recorder::new();
+        Text::new(10).draw("abc", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        assert_eq!(recorder.commands.len(), 3);
+    }
+
+    #[test]
+    fn test_subpixel() {
+        let c = Context::new_abs(64.0, 64.0);
+        // Characters 10.5 wide with images at the whole pixel below the pen.
+        let mut cache = Font::new(Texture::new(0, 8, 8)).advance(10.5).subpixel();
+        let mut recorder = Recorder::new();
+        Text::new(10).draw("aaa", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        match recorder.commands[..] {
+            [Command::TriListUvIndexed { ref chunks, .. }] => {
+                let mut xs: Vec<f32> = chunks[0].vertices.iter()
+                    .map(|v| (v[0] + 1.0) * 32.0)
+                    .collect();
+                xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                xs.dedup();
+                // Pens at 0, 10.5 and 21.
+                assert_eq!(xs, vec![0.0, 8.0, 10.0, 18.0, 21.0, 29.0]);
+            }
+            _ => panic!("expected indexed triangles"),
+        }
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let c = Context::new_abs(64.0, 64.0);
+        // A distance field rendered at half the drawn size.
+        let mut cache = Font::new(Texture::new(0, 8, 8))
+            .offset([0.0, 16.0])
+            .advance(20.0)
+            .atlas([0.0, 0.0, 8.0, 8.0], [16.0, 16.0])
+            .distance_field(4.0);
+        let mut recorder = Recorder::new();
+        Text::new(10).draw("ab", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        match recorder.commands[..] {
+            [Command::TriListUvSdf { ref chunks, .. }] => {
+                let xs: Vec<f32> = chunks[0].vertices.iter().map(|v| v[0]).collect();
+                let uvs: Vec<f32> = chunks[0].texture_coords.iter().map(|uv| uv[0]).collect();
+                // Quads of 16 pixels at 0 and 20 pixels, in normalized coordinates.
+                assert_eq!(xs.iter().cloned().fold(1.0, f32::min), -1.0);
+                assert_eq!(xs.iter().cloned().fold(-1.0, f32::max), 0.125);
+                assert_eq!(uvs.iter().cloned().fold(0.0, f32::max), 1.0);
+                assert_eq!(xs.len(), 12);
+            }
+            _ => panic!("expected distance field triangles"),
+        }
+    }
+
+    #[test]
+    fn test_decorations() {
+        let c = Context::new_abs(64.0, 64.0);
+        let mut cache = atlas();
+        let mut recorder = Recorder::new();
+        Text::new(10).underline().strikethrough()
+            .draw("abc", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        // The default metrics have an ascent of 8 and a descent of 2,
+        // giving lines at 1 below and 2.4 above the baseline.
+        let rects: Vec<[f32; 4]> = recorder.commands[1..].iter().map(|command| match *command {
+            Command::TriList { ref chunks, .. } => {
+                let xs = chunks[0].iter().map(|v| (v[0] + 1.0) * 32.0);
+                let ys = chunks[0].iter().map(|v| (1.0 - v[1]) * 32.0);
+                [xs.clone().fold(64.0, f32::min), ys.clone().fold(64.0, f32::min),
+                 xs.fold(-64.0, f32::max), ys.fold(-64.0, f32::max)]
+            }
+            _ => panic!("expected a rectangle"),
+        }).collect();
+        assert_eq!(rects.len(), 2);
+        assert_eq!(rects[0], [0.0, 0.5, 30.0, 1.5]);
+        assert!((rects[1][1] + 2.9).abs() < 1e-5 && (rects[1][3] + 1.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_outline_shadow() {
+        let c = Context::new_abs(64.0, 64.0);
+        let mut cache = atlas();
+        let mut recorder = Recorder::new();
+        let (red, blue) = ([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]);
+        Text::new(10).outline(red, 1.0).shadow(blue, [2.0, 2.0])
+            .draw("a", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        let layers: Vec<([f32; 4], usize)> = recorder.commands.iter().map(|command| match *command {
+            Command::TriListUvIndexed { color, ref chunks, .. } => {
+                (color, chunks[0].indices.len() / 6)
+            }
+            _ => panic!("expected indexed triangles"),
+        }).collect();
+        // The outline of a pixel is eight copies around the character, drawn at once.
+        assert_eq!(layers, vec![(blue, 8), (red, 8), ([0.0, 0.0, 0.0, 1.0], 1)]);
+    }
+
+    #[test]
+    fn test_outline_step() {
+        assert_eq!(outline_offsets(0.0, 1.0), vec![[0.0; 2]]);
+        assert_eq!(outline_offsets(2.0, 1.0).len(), 13 + 8);
+        // Rings half a unit apart, for text scaled up twice.
+        assert_eq!(outline_offsets(2.0, 0.5).len(), 26 + 19 + 13 + 8);
+        // At most 16 rings.
+        assert_eq!(outline_offsets(1.0, 1e-9).len(), outline_offsets(1.0, 1.0 / 16.0).len());
+    }
+
+    #[test]
+    fn test_outline_distance_field() {
+        let c = Context::new_abs(64.0, 64.0);
+        // A distance field with a spread of 4 texels, rendered at half the drawn size.
+        let mut cache = Font::new(Texture::new(0, 8, 8))
+            .atlas([0.0, 0.0, 8.0, 8.0], [16.0, 16.0])
+            .distance_field(4.0);
+        let mut recorder = Recorder::new();
+        let red = [1.0, 0.0, 0.0, 1.0];
+        Text::new(10).outline(red, 2.0)
+            .draw("ab", &mut cache, &c.draw_state, c.transform, &mut recorder).unwrap();
+        let layers: Vec<([f32; 4], f32, usize)> = recorder.commands.iter().map(|command| {
+            match *command {
+                Command::TriListUvSdf { color, threshold, ref chunks, .. } => {
+                    (color, threshold, chunks[0].vertices.len() / 6)
+                }
+                _ => panic!("expected distance field triangles"),
+            }
+        }).collect();
+        // The outline is a texel outside the characters, without stamps.
+        assert_eq!(layers, vec![(red, 0.375, 2), ([0.0, 0.0, 0.0, 1.0], 0.5, 2)]);
+    }
 }
@@ -0,0 +1,526 @@
+//! Lay out text in multiple lines
+//!
+//! Text is broken into lines at newlines,
+//! and at spaces when a line gets wider than the maximum width.
+//! The result is a list of positioned glyphs that can be drawn
+//! with `Text::draw_layout`.
+//!
+//! The baseline of the first line is at `y = 0`, like `Text::draw`.
+//!
+//! A layout also maps between points and byte indices in the text,
+//! for placing a caret and drawing selections in text input.
+//! `Text::draw` draws text on a single line without breaking it,
+//! so its characters are where `TextLayout::new` puts them
+//! only for text without newlines.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use character::{CharacterCache, FontMetrics};
+use math::{Scalar, Vec2d};
+use types::{FontSize, Rectangle};
+
+/// The horizontal alignment of lines
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Align {
+    /// Align lines to the left edge.
+    Left,
+    /// Center lines.
+    Center,
+    /// Align lines to the right edge.
+    Right,
+    /// Stretch spaces so lines fill the maximum width.
+    ///
+    /// The last line of a paragraph is aligned to the left.
+    /// Without a maximum width, this is the same as `Left`.
+    Justify,
+}
+
+/// A character with a position
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    /// The character.
+    pub ch: char,
+    /// The byte index of the character in the text.
+    pub index: usize,
+    /// The pen position on the baseline.
+    pub position: [Scalar; 2],
+    /// The distance to the next pen position, including justification.
+    pub advance: Scalar,
+}
+
+/// A line of laid out text
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutLine {
+    /// The byte range of the line in the text, without line break.
+    pub range: Range<usize>,
+    /// The range of the line in the glyph list.
+    pub glyphs: Range<usize>,
+    /// The left edge of the line.
+    pub x: Scalar,
+    /// The baseline of the line.
+    pub baseline: Scalar,
+    /// The width of the line, without spaces at a wrap.
+    pub width: Scalar,
+}
+
+/// Text broken into lines
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// The glyphs of all lines.
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The lines.
+    pub lines: Vec<LayoutLine>,
+    /// The distance between baselines.
+    pub line_height: Scalar,
+    /// The width of the widest line, or the maximum width when wrapping.
+    pub width: Scalar,
+    /// The vertical metrics of the font.
+    pub metrics: FontMetrics,
+}
+
+impl Layout {
+    /// Returns the height from the top of the first line
+    /// to the bottom of the last line.
+    pub fn height(&self) -> Scalar {
+        self.lines.len() as Scalar * self.line_height
+    }
+
+    /// Returns the byte index of the grapheme boundary closest to a point.
+    ///
+    /// The text must be the laid out text.
+    /// Points above or below the text hit the first or last line,
+    /// and points beside a line hit its start or end.
+    pub fn hit_test(&self, text: &str, point: Vec2d) -> usize {
+        let top = -self.metrics.ascent;
+        let n = ((point[1] - top) / self.line_height).floor().max(0.0) as usize;
+        let line = match self.lines.get(n).or_else(|| self.lines.last()) {
+            Some(line) => line,
+            None => return 0,
+        };
+        let start = line.range.start;
+        text[line.range.clone()].grapheme_indices(true)
+            .map(|(i, _)| start + i)
+            .chain(Some(line.range.end))
+            .map(|index| (index, (self.line_x(line, index) - point[0]).abs()))
+            .fold(None, |best: Option<(usize, Scalar)>, (index, d)| match best {
+                Some(best) if best.1 <= d => Some(best),
+                _ => Some((index, d)),
+            })
+            .map(|best| best.0)
+            .unwrap_or(start)
+    }
+
+    /// Returns the rectangle of a caret before the character at a byte index.
+    ///
+    /// The caret has no width, and spans the ascent and descent of the font.
+    /// At the end of a wrapped line, the caret is at the start of the next line.
+    pub fn caret(&self, index: usize) -> Rectangle {
+        let line = self.line(index);
+        [self.line_x(line, index),
+         line.baseline - self.metrics.ascent,
+         0.0,
+         self.metrics.height()]
+    }
+
+    /// Returns the rectangles highlighting a range of bytes, one per line.
+    ///
+    /// Rectangles span the line height, so the lines of a selection touch.
+    /// Lines where nothing is selected are skipped.
+    pub fn selection(&self, range: Range<usize>) -> Vec<Rectangle> {
+        self.lines.iter()
+            .filter_map(|line| {
+                let start = range.start.max(line.range.start);
+                let end = range.end.min(line.range.end);
+                if start >= end {
+                    return None;
+                }
+                let x = self.line_x(line, start);
+                Some([x,
+                      line.baseline - self.metrics.ascent,
+                      self.line_x(line, end) - x,
+                      self.line_height])
+            })
+            .collect()
+    }
+
+    /// Returns the last line starting at or before a byte index.
+    fn line(&self, index: usize) -> &LayoutLine {
+        self.lines.iter()
+            .take_while(|line| line.range.start <= index)
+            .last()
+            .unwrap_or(&self.lines[0])
+    }
+
+    /// Returns the pen position of a byte index on a line.
+    ///
+    /// Indices past the glyphs of the line are at the end of the line.
+    fn line_x(&self, line: &LayoutLine, index: usize) -> Scalar {
+        let glyphs = &self.glyphs[line.glyphs.clone()];
+        match glyphs.iter().find(|glyph| glyph.index >= index) {
+            Some(glyph) => glyph.position[0],
+            None => glyphs.last().map(|glyph| glyph.position[0] + glyph.advance).unwrap_or(line.x),
+        }
+    }
+}
+
+/// Settings for laying out text
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    /// The font size
+    pub font_size: FontSize,
+    /// The width where lines wrap, if any
+    pub max_width: Option<Scalar>,
+    /// The horizontal alignment
+    pub align: Align,
+    /// The distance between baselines,
+    /// or `None` to use the line height of the font metrics
+    pub line_height: Option<Scalar>,
+}
+
+impl TextLayout {
+    /// Creates a new layout without wrapping, aligned to the left.
+    pub fn new(font_size: FontSize) -> TextLayout {
+        TextLayout {
+            font_size,
+            max_width: None,
+            align: Align::Left,
+            line_height: None,
+        }
+    }
+
+    /// Sets font size.
+    pub fn font_size(mut self, value: FontSize) -> Self {
+        self.font_size = value;
+        self
+    }
+
+    /// Sets the width where lines wrap.
+    pub fn max_width(mut self, value: Scalar) -> Self {
+        self.max_width = Some(value);
+        self
+    }
+
+    /// Sets the width where lines wrap, if any.
+    pub fn maybe_max_width(mut self, value: Option<Scalar>) -> Self {
+        self.max_width = value;
+        self
+    }
+
+    /// Sets alignment.
+    pub fn align(mut self, value: Align) -> Self {
+        self.align = value;
+        self
+    }
+
+    /// Sets the distance between baselines.
+    pub fn line_height(mut self, value: Scalar) -> Self {
+        self.line_height = Some(value);
+        self
+    }
+
+    /// Lays out text.
+    pub fn layout<C>(&self, text: &str, cache: &mut C) -> Result<Layout, C::Error>
+        where C: CharacterCache
+    {
+        let metrics = cache.metrics(self.font_size)?;
+        let line_height = self.line_height.unwrap_or_else(|| metrics.line_height());
+        let mut glyphs = vec![];
+        let mut lines: Vec<LayoutLine> = vec![];
+        let mut paragraph_start = 0;
+        for raw in text.split('\n') {
+            let paragraph = raw.trim_end_matches('\r');
+            let first_line = lines.len();
+            self.break_paragraph(paragraph_start, paragraph, cache, &mut glyphs, &mut lines)?;
+            // The last line of a paragraph is not justified.
+            let last_line = lines.len() - 1;
+            for (i, line) in lines[first_line..].iter_mut().enumerate() {
+                line.baseline = (first_line + i) as Scalar * line_height;
+                if first_line + i != last_line {
+                    self.justify(line, &mut glyphs);
+                }
+            }
+            paragraph_start += raw.len() + 1;
+        }
+
+        let width = self.max_width.unwrap_or_else(|| {
+            lines.iter().map(|line| line.width).fold(0.0, Scalar::max)
+        });
+        for line in &mut lines {
+            line.x = match self.align {
+                Align::Left | Align::Justify => 0.0,
+                Align::Center => 0.5 * (width - line.width),
+                Align::Right => width - line.width,
+            };
+            for glyph in &mut glyphs[line.glyphs.clone()] {
+                glyph.position[0] += line.x;
+                glyph.position[1] = line.baseline;
+            }
+        }
+        Ok(Layout {
+            glyphs,
+            lines,
+            line_height,
+            width,
+            metrics,
+        })
+    }
+
+    /// Breaks a paragraph into lines, with glyph positions relative to each line.
+    fn break_paragraph<C>(&self,
+                          start: usize,
+                          paragraph: &str,
+                          cache: &mut C,
+                          glyphs: &mut Vec<PositionedGlyph>,
+                          lines: &mut Vec<LayoutLine>)
+                          -> Result<(), C::Error>
+        where C: CharacterCache
+    {
+        // The associated float constants need Rust 1.43.
+        #[allow(clippy::legacy_numeric_constants)]
+        let max_width = self.max_width.unwrap_or(::std::f64::INFINITY);
+        let mut line = LayoutLine {
+            range: start..start,
+            glyphs: glyphs.len()..glyphs.len(),
+            x: 0.0,
+            baseline: 0.0,
+            width: 0.0,
+        };
+        // The pen position, including trailing spaces.
+        let mut x = 0.0;
+        // The state at the last space, where the line can be broken.
+        let mut wrap: Option<(usize, usize, Scalar)> = None;
+        let mut prev = None;
+        for (i, ch) in paragraph.char_indices() {
+            let index = start + i;
+            let kerning = match prev {
+                Some(prev) => cache.kerning(self.font_size, prev, ch)?,
+                None => 0.0,
+            };
+            prev = Some(ch);
+            let advance = cache.character(self.font_size, ch)?.width();
+            if ch.is_whitespace() {
+                x += kern(&mut glyphs[line.glyphs.start..], kerning);
+                if line.glyphs.end == glyphs.len() && line.glyphs.end > line.glyphs.start {
+                    wrap = Some((glyphs.len(), index, line.width));
+                }
+                glyphs.push(PositionedGlyph { ch, index, position: [x, 0.0], advance });
+                x += advance;
+                continue;
+            }
+            if x + kerning + advance > max_width && glyphs.len() > line.glyphs.start {
+                let (moved, next_index) = match wrap.take() {
+                    Some((glyph, range_end, width)) => {
+                        // Wrap at the last space, dropping the spaces.
+                        let next_glyph = glyphs[glyph..].iter()
+                            .position(|g| !g.ch.is_whitespace())
+                            .map(|n| glyph + n)
+                            .unwrap_or_else(|| glyphs.len());
+                        let next_index = glyphs.get(next_glyph).map(|g| g.index).unwrap_or(index);
+                        let moved: Vec<PositionedGlyph> = glyphs.drain(next_glyph..).collect();
+                        glyphs.truncate(glyph);
+                        line.glyphs.end = glyph;
+                        line.range.end = range_end;
+                        line.width = width;
+                        (moved, next_index)
+                    }
+                    None => {
+                        // A word wider than a line is broken between characters.
+                        line.glyphs.end = glyphs.len();
+                        line.range.end = index;
+                        line.width = x;
+                        (vec![], index)
+                    }
+                };
+                lines.push(line.clone());
+                line = LayoutLine {
+                    range: next_index..next_index,
+                    glyphs: glyphs.len()..glyphs.len(),
+                    x: 0.0,
+                    baseline: 0.0,
+                    width: 0.0,
+                };
+                x = 0.0;
+                let offset = moved.first().map(|g| g.position[0]).unwrap_or(0.0);
+                for mut glyph in moved {
+                    glyph.position[0] -= offset;
+                    x = glyph.position[0] + glyph.advance;
+                    glyphs.push(glyph);
+                }
+                line.width = x;
+                line.glyphs.end = glyphs.len();
+            }
+            x += kern(&mut glyphs[line.glyphs.start..], kerning);
+            glyphs.push(PositionedGlyph { ch, index, position: [x, 0.0], advance });
+            x += advance;
+            line.width = x;
+            line.glyphs.end = glyphs.len();
+        }
+        // Keep trailing spaces of the last line, since they were typed.
+        line.glyphs.end = glyphs.len();
+        line.range.end = start + paragraph.len();
+        lines.push(line);
+        Ok(())
+    }
+
+    /// Stretches the spaces of a line to fill the maximum width.
+    fn justify(&self, line: &mut LayoutLine, glyphs: &mut [PositionedGlyph]) {
+        let max_width = match (self.align, self.max_width) {
+            (Align::Justify, Some(max_width)) => max_width,
+            _ => return,
+        };
+        let glyphs = &mut glyphs[line.glyphs.clone()];
+        let spaces = glyphs.iter().filter(|g| g.ch.is_whitespace()).count();
+        if spaces == 0 || line.width >= max_width {
+            return;
+        }
+        let extra = (max_width - line.width) / spaces as Scalar;
+        let mut shift = 0.0;
+        for glyph in glyphs {
+            glyph.position[0] += shift;
+            if glyph.ch.is_whitespace() {
+                glyph.advance += extra;
+                shift += extra;
+            }
+        }
+        line.width = max_width;
+    }
+}
+
+/// Adds kerning to the advance of the last glyph of a line, if any.
+///
+/// Returns the change of the pen position.
+fn kern(line: &mut [PositionedGlyph], kerning: Scalar) -> Scalar {
+    match line.last_mut() {
+        Some(glyph) => {
+            glyph.advance += kerning;
+            kerning
+        }
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mock::Font;
+    use recorder::Texture;
+
+    /// A font where every character is 10 wide, with kerning for "AV".
+    ///
+    /// The glyphs are 8 wide, 12 high, and go 2 below the baseline.
+    fn monospace() -> Font {
+        Font::new(Texture::new(0, 8, 12))
+            .offset([1.0, 10.0])
+            .atlas([0.0, 0.0, 8.0, 12.0], [8.0, 12.0])
+            .kerning('A', 'V', -3.0)
+    }
+
+    fn line_texts<'a>(text: &'a str, layout: &Layout) -> Vec<&'a str> {
+        layout.lines.iter().map(|line| &text[line.range.clone()]).collect()
+    }
+
+    #[test]
+    fn test_newlines() {
+        let text = "ab\r\ncd\n\ne";
+        let layout = TextLayout::new(10).line_height(15.0).layout(text, &mut monospace()).unwrap();
+        assert_eq!(line_texts(text, &layout), vec!["ab", "cd", "", "e"]);
+        assert_eq!(layout.lines[3].baseline, 45.0);
+        assert_eq!(layout.glyphs[4].ch, 'e');
+        assert_eq!(layout.glyphs[4].position, [0.0, 45.0]);
+        assert_eq!(layout.width, 20.0);
+    }
+
+    #[test]
+    fn test_metrics_line_height() {
+        let layout = TextLayout::new(10).layout("a\nb", &mut monospace()).unwrap();
+        assert_eq!(layout.line_height, 10.0);
+        assert_eq!(layout.lines[1].baseline, 10.0);
+        assert_eq!(layout.height(), 20.0);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let text = "one two  three abcdefgh";
+        let layout = TextLayout::new(10).max_width(65.0).layout(text, &mut monospace()).unwrap();
+        assert_eq!(line_texts(text, &layout), vec!["one", "two", "three", "abcdef", "gh"]);
+        for line in &layout.lines {
+            assert!(line.width <= 65.0);
+            let first = &layout.glyphs[line.glyphs.start];
+            assert_eq!(first.position[0], 0.0);
+            assert_eq!(first.index, line.range.start);
+        }
+    }
+
+    #[test]
+    fn test_align() {
+        let text = "a bb\nccc";
+        let mut cache = monospace();
+        let layout = TextLayout::new(10).align(Align::Right).layout(text, &mut cache).unwrap();
+        assert_eq!(layout.lines[1].x, 10.0);
+        let layout = TextLayout::new(10).align(Align::Center).max_width(60.0)
+            .layout(text, &mut cache).unwrap();
+        assert_eq!(layout.lines[0].x, 10.0);
+        assert_eq!(layout.lines[1].x, 15.0);
+
+        let text = "a b c dddd";
+        let layout = TextLayout::new(10).align(Align::Justify).max_width(70.0)
+            .layout(text, &mut cache).unwrap();
+        assert_eq!(line_texts(text, &layout), vec!["a b c", "dddd"]);
+        assert_eq!(layout.lines[0].width, 70.0);
+        let xs: Vec<Scalar> = layout.glyphs[..5].iter().map(|g| g.position[0]).collect();
+        assert_eq!(xs, vec![0.0, 10.0, 30.0, 40.0, 60.0]);
+        assert_eq!(layout.lines[1].width, 40.0);
+    }
+
+    #[test]
+    fn test_kerning() {
+        let mut cache = monospace();
+        assert_eq!(cache.width(10, "AVA"), Ok(27.0));
+        let layout = TextLayout::new(10).layout("AVA", &mut cache).unwrap();
+        assert_eq!(layout.glyphs[0].advance, 7.0);
+        assert_eq!(layout.glyphs[1].position[0], 7.0);
+        assert_eq!(layout.lines[0].width, 27.0);
+
+        // Kerning is not applied across a wrap.
+        let layout = TextLayout::new(10).max_width(15.0).layout("AV", &mut cache).unwrap();
+        assert_eq!(layout.lines[0].width, 10.0);
+        assert_eq!(layout.glyphs[0].advance, 10.0);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        // The accented e is one grapheme of three bytes.
+        let text = "ab e\u{301}\ncd";
+        let layout = TextLayout::new(10).layout(text, &mut monospace()).unwrap();
+        assert_eq!(layout.hit_test(text, [-5.0, 0.0]), 0);
+        assert_eq!(layout.hit_test(text, [34.0, 0.0]), 3);
+        assert_eq!(layout.hit_test(text, [44.0, 0.0]), 6);
+        assert_eq!(layout.hit_test(text, [12.0, 9.0]), 8);
+        assert_eq!(layout.hit_test(text, [100.0, -50.0]), 6);
+        assert_eq!(layout.hit_test(text, [100.0, 50.0]), 9);
+    }
+
+    #[test]
+    fn test_caret() {
+        let text = "ab\ncd";
+        let layout = TextLayout::new(10).layout(text, &mut monospace()).unwrap();
+        assert_eq!(layout.caret(0), [0.0, -8.0, 0.0, 10.0]);
+        assert_eq!(layout.caret(2), [20.0, -8.0, 0.0, 10.0]);
+        assert_eq!(layout.caret(4), [10.0, 2.0, 0.0, 10.0]);
+
+        // The end of a word broken between lines is the start of the next line.
+        let layout = TextLayout::new(10).max_width(65.0).layout("abcdefgh", &mut monospace()).unwrap();
+        assert_eq!(layout.caret(6), [0.0, 2.0, 0.0, 10.0]);
+        assert_eq!(layout.caret(8), [20.0, 2.0, 0.0, 10.0]);
+    }
+
+    #[test]
+    fn test_selection() {
+        let text = "ab e\ncd\n\nf";
+        let layout = TextLayout::new(10).align(Align::Right).layout(text, &mut monospace()).unwrap();
+        assert_eq!(layout.selection(1..6), vec![[10.0, -8.0, 30.0, 10.0], [20.0, 2.0, 10.0, 10.0]]);
+        assert!(layout.selection(2..2).is_empty());
+    }
+}
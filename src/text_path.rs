@@ -0,0 +1,194 @@
+//! Place text along a curve
+//!
+//! A `TextPath` is a polyline used as the baseline of text,
+//! for example for map labels or the markings of a dial.
+//! Every glyph is rotated to the direction of the path at its middle.
+//! Draw text along a path with `Text::draw_path`.
+
+use character::CharacterCache;
+use math::{Scalar, Vec2d};
+use radians::Radians;
+use text_layout::Align;
+use types::{FontSize, Rectangle, Resolution};
+
+/// A character placed on a path
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathGlyph {
+    /// The character.
+    pub ch: char,
+    /// The point on the path below the middle of the character.
+    pub position: Vec2d,
+    /// The unit direction of the path at the position.
+    pub direction: Vec2d,
+    /// The advance width of the character.
+    pub advance: Scalar,
+}
+
+/// A baseline for text following a polyline
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextPath {
+    /// The points of the polyline.
+    pub points: Vec<Vec2d>,
+    /// The distance along the path to move the text.
+    pub offset: Scalar,
+    /// The placement of the text on the path.
+    ///
+    /// `Justify` spreads the characters over the length of the path.
+    pub align: Align,
+}
+
+impl TextPath {
+    /// Creates a path through points, with text at the start.
+    pub fn new(points: Vec<Vec2d>) -> TextPath {
+        TextPath {
+            points,
+            offset: 0.0,
+            align: Align::Left,
+        }
+    }
+
+    /// Creates a path along the ellipse in a rectangle,
+    /// from the start to the end angle in radians.
+    ///
+    /// Like `CircleArc`, angles increase clockwise on the screen,
+    /// and the resolution is the number of lines in a full turn.
+    pub fn arc(rect: Rectangle, start: Scalar, end: Scalar, resolution: Resolution) -> TextPath {
+        let (rx, ry) = (0.5 * rect[2], 0.5 * rect[3]);
+        let (cx, cy) = (rect[0] + rx, rect[1] + ry);
+        let twopi = <Scalar as Radians>::_360();
+        let delta = (((end - start) % twopi) + twopi) % twopi;
+        let n = (delta / (twopi / resolution.max(1) as Scalar)).ceil().max(1.0) as usize;
+        let points = (0..n + 1).map(|i| {
+            let angle = start + delta * i as Scalar / n as Scalar;
+            [cx + rx * angle.cos(), cy + ry * angle.sin()]
+        }).collect();
+        TextPath::new(points)
+    }
+
+    /// Sets the distance along the path to move the text.
+    pub fn offset(mut self, value: Scalar) -> Self {
+        self.offset = value;
+        self
+    }
+
+    /// Sets the placement of the text on the path.
+    pub fn align(mut self, value: Align) -> Self {
+        self.align = value;
+        self
+    }
+
+    /// Returns the length of the path.
+    pub fn length(&self) -> Scalar {
+        self.points.windows(2).map(|w| distance(w[0], w[1])).sum()
+    }
+
+    /// Returns the point and the unit direction at a distance along the path.
+    ///
+    /// Distances before the start or past the end follow the first or last line.
+    pub fn point_at(&self, distance_along: Scalar) -> (Vec2d, Vec2d) {
+        let mut start = 0.0;
+        let mut lines = self.points.windows(2)
+            .map(|w| (w[0], w[1], distance(w[0], w[1])))
+            .filter(|&(_, _, len)| len > 0.0)
+            .peekable();
+        while let Some((a, b, len)) = lines.next() {
+            if distance_along <= start + len || lines.peek().is_none() {
+                let direction = [(b[0] - a[0]) / len, (b[1] - a[1]) / len];
+                let t = distance_along - start;
+                return ([a[0] + direction[0] * t, a[1] + direction[1] * t], direction);
+            }
+            start += len;
+        }
+        (self.points.first().cloned().unwrap_or([0.0; 2]), [1.0, 0.0])
+    }
+
+    /// Places the characters of a text on the path.
+    ///
+    /// Characters are kerned like `Text::draw`.
+    /// Characters past the ends of the path follow the first or last line.
+    pub fn place<C>(&self, text: &str, font_size: FontSize, cache: &mut C)
+                    -> Result<Vec<PathGlyph>, C::Error>
+        where C: CharacterCache
+    {
+        // Pen positions along a straight line.
+        let mut pens: Vec<(char, Scalar, Scalar)> = vec![];
+        let mut x = 0.0;
+        let mut prev = None;
+        for ch in text.chars() {
+            if let Some(prev) = prev {
+                x += cache.kerning(font_size, prev, ch)?;
+            }
+            prev = Some(ch);
+            let advance = cache.character(font_size, ch)?.width();
+            pens.push((ch, x, advance));
+            x += advance;
+        }
+        let length = self.length();
+        let (start, spacing) = match self.align {
+            Align::Left => (0.0, 0.0),
+            Align::Center => (0.5 * (length - x), 0.0),
+            Align::Right => (length - x, 0.0),
+            Align::Justify if pens.len() > 1 => {
+                (0.0, ((length - self.offset - x) / (pens.len() - 1) as Scalar).max(0.0))
+            }
+            Align::Justify => (0.0, 0.0),
+        };
+        Ok(pens.into_iter().enumerate().map(|(i, (ch, pen, advance))| {
+            let middle = start + self.offset + pen + i as Scalar * spacing + 0.5 * advance;
+            let (position, direction) = self.point_at(middle);
+            PathGlyph { ch, position, direction, advance }
+        }).collect())
+    }
+}
+
+fn distance(a: Vec2d, b: Vec2d) -> Scalar {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mock::Font;
+    use recorder::Texture;
+
+    #[test]
+    fn test_point_at() {
+        let path = TextPath::new(vec![[0.0, 0.0], [10.0, 0.0], [10.0, 0.0], [10.0, 20.0]]);
+        assert_eq!(path.length(), 30.0);
+        assert_eq!(path.point_at(5.0), ([5.0, 0.0], [1.0, 0.0]));
+        assert_eq!(path.point_at(15.0), ([10.0, 5.0], [0.0, 1.0]));
+        // The ends are extended.
+        assert_eq!(path.point_at(-5.0), ([-5.0, 0.0], [1.0, 0.0]));
+        assert_eq!(path.point_at(40.0), ([10.0, 30.0], [0.0, 1.0]));
+        assert_eq!(TextPath::new(vec![[1.0, 2.0]]).point_at(5.0), ([1.0, 2.0], [1.0, 0.0]));
+    }
+
+    #[test]
+    fn test_arc() {
+        use std::f64::consts::PI;
+
+        let path = TextPath::arc([0.0, 0.0, 20.0, 20.0], PI, 2.0 * PI, 64);
+        assert_eq!(path.points.len(), 33);
+        for p in &path.points {
+            assert!((((p[0] - 10.0).powi(2) + (p[1] - 10.0).powi(2)).sqrt() - 10.0).abs() < 1e-9);
+        }
+        // Clockwise on the screen, the top half is drawn left to right.
+        let (top, direction) = path.point_at(0.5 * path.length());
+        assert!((top[0] - 10.0).abs() < 1e-9 && top[1] < 0.1);
+        assert!(direction[0] > 0.99 && direction[1].abs() < 0.1);
+    }
+
+    #[test]
+    fn test_place() {
+        let mut cache = Font::new(Texture::new(0, 8, 8));
+        let path = TextPath::new(vec![[0.0, 0.0], [100.0, 0.0]]);
+        let xs = |path: &TextPath, cache: &mut Font| -> Vec<Scalar> {
+            path.place("abc", 10, cache).unwrap().iter().map(|g| g.position[0]).collect()
+        };
+        assert_eq!(xs(&path, &mut cache), vec![5.0, 15.0, 25.0]);
+        assert_eq!(xs(&path.clone().offset(10.0), &mut cache), vec![15.0, 25.0, 35.0]);
+        assert_eq!(xs(&path.clone().align(Align::Center), &mut cache), vec![40.0, 50.0, 60.0]);
+        assert_eq!(xs(&path.clone().align(Align::Right), &mut cache), vec![75.0, 85.0, 95.0]);
+        assert_eq!(xs(&path.clone().align(Align::Justify), &mut cache), vec![5.0, 50.0, 95.0]);
+    }
+}
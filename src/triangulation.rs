@@ -5,7 +5,12 @@ use BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
 use interpolation::lerp;
 use types::{Line, SourceRectangle, Polygon, Polygons, Radius, Rectangle, Resolution};
 use math::{multiply, orient, translate, Matrix2d, Scalar, Vec2d};
+use modular_index::previous;
+use complex_polygon::FillRule;
+use line::Shape;
+use polyline::Join;
 use radians::Radians;
+use std::cmp::Ordering;
 
 /// Transformed x coordinate as f32.
 #[inline(always)]
@@ -40,19 +45,8 @@ pub fn with_lerp_polygons_tri_list<F>(m: Matrix2d, polygons: Polygons, tween_fac
     let p1 = polygons[next_frame];
     // Get factor between frames.
     let tw = tw - frame as Scalar;
-    let n = polygons[0].len();
-    let mut i: usize = 0;
-    stream_polygon_tri_list(m,
-                            || {
-        if i >= n {
-            return None;
-        }
-
-        let j = i;
-        i += 1;
-        Some(lerp(&p0[j], &p1[j], &tw))
-    },
-                            f);
+    let polygon: Vec<Vec2d> = p0.iter().zip(p1).map(|(a, b)| lerp(a, b, &tw)).collect();
+    with_polygon_tri_list(m, &polygon, f);
 }
 
 /// Streams an ellipse specified by a resolution.
@@ -549,27 +543,624 @@ pub fn stream_quad_tri_list<E, F>(m: Matrix2d, mut quad_edge: E, mut f: F)
     }
 }
 
-/// Splits polygon into convex segments.
+/// Splits polygon into triangles.
 /// Create a buffer that fits into L1 cache with 1KB overhead.
 ///
-/// See stream_polygon_tri_list docs for detailed explanation.
+/// The polygon can be convex or concave, but should not intersect itself.
+/// Convex polygons are split into a fan of triangles from the first point,
+/// other polygons with `triangulate_polygon`.
 pub fn with_polygon_tri_list<F>(m: Matrix2d, polygon: Polygon, f: F)
     where F: FnMut(&[[f32; 2]])
 {
+    if is_convex(polygon) {
+        let mut points = polygon.iter();
+        stream_polygon_tri_list(m, || points.next().cloned(), f);
+    } else {
+        let indices = triangulate_polygon(polygon);
+        stream_indexed_tri_list(m, polygon, &indices, f);
+    }
+}
 
+/// Returns `true` if all corners of a polygon turn the same way,
+/// going around once.
+///
+/// Corners with zero area are ignored.
+fn is_convex(polygon: Polygon) -> bool {
     let n = polygon.len();
+    let mut sign = 0.0;
+    // A convex polygon changes between moving left and right at most twice.
+    let mut dx_sign = 0.0;
+    let mut dx_changes = 0;
+    for i in 0..n {
+        let (a, b, c) = (polygon[previous(n, i)], polygon[i], polygon[(i + 1) % n]);
+        let cross = (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0]);
+        if cross != 0.0 {
+            if sign * cross < 0.0 {
+                return false;
+            }
+            sign = cross.signum();
+        }
+        let dx = c[0] - b[0];
+        if dx != 0.0 {
+            if dx_sign * dx < 0.0 {
+                dx_changes += 1;
+            }
+            dx_sign = dx.signum();
+        }
+    }
+    dx_changes <= 2
+}
+
+/// Computes triangle indices for a simple polygon using ear clipping.
+///
+/// Returns three indices into `polygon` per triangle.
+/// The polygon can be convex or concave, with clockwise or
+/// counter-clockwise winding.
+/// Triangles with zero area are skipped.
+///
+/// A simple polygon is one that does not intersect itself.
+/// Self-intersecting polygons still produce triangles,
+/// but they might not cover the expected area.
+pub fn triangulate_polygon(polygon: Polygon) -> Vec<usize> {
+    let n = polygon.len();
+    let mut indices = Vec::with_capacity(3 * n.saturating_sub(2));
+    if n < 3 {
+        return indices;
+    }
+
+    // Use the sign of the area to make convex corners positive.
+    let mut area = 0.0;
+    for i in 0..n {
+        let (p, q) = (polygon[previous(n, i)], polygon[i]);
+        area += p[0] * q[1] - q[0] * p[1];
+    }
+    let sign = if area < 0.0 { -1.0 } else { 1.0 };
+    let corner = |a: usize, b: usize, c: usize| -> Scalar {
+        let (a, b, c) = (polygon[a], polygon[b], polygon[c]);
+        sign * ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]))
+    };
+
+    // The remaining corners are linked in a ring.
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut prev: Vec<usize> = (0..n).map(|i| previous(n, i)).collect();
+    // Only corners that are not convex can be inside an ear.
+    let mut reflex: Vec<bool> = (0..n).map(|i| corner(prev[i], i, next[i]) <= 0.0).collect();
+    let mut reflex_corners: Vec<usize> = (0..n).filter(|&i| reflex[i]).collect();
+    let mut len = n;
+    let mut b = 0;
+    // Counts the corners visited since the last ear was clipped.
+    let mut visited = 0;
+    while len > 3 {
+        let (a, c) = (prev[b], next[b]);
+        let cross = corner(a, b, c);
+        let is_ear = cross > 0.0 &&
+                     !reflex_corners.iter().any(|&j| {
+            j != a && j != b && j != c && polygon[j] != polygon[a] && polygon[j] != polygon[b] &&
+            polygon[j] != polygon[c] &&
+            corner(a, b, j) >= 0.0 && corner(b, c, j) >= 0.0 && corner(c, a, j) >= 0.0
+        });
+        if is_ear || cross == 0.0 || visited > len {
+            // Degenerate corners are removed without a triangle.
+            // When no ear is found, the polygon intersects itself,
+            // and the corner is clipped anyway to make progress.
+            if cross != 0.0 {
+                indices.extend_from_slice(&[a, b, c]);
+            }
+            next[a] = c;
+            prev[c] = a;
+            len -= 1;
+            // Only the corners next to the clipped one change.
+            reflex[b] = false;
+            for &j in &[a, c] {
+                let is_reflex = corner(prev[j], j, next[j]) <= 0.0;
+                if is_reflex && !reflex[j] {
+                    reflex_corners.push(j);
+                }
+                reflex[j] = is_reflex;
+            }
+            reflex_corners.retain(|&j| reflex[j]);
+            visited = 0;
+        } else {
+            visited += 1;
+        }
+        b = c;
+    }
+    let (a, c) = (prev[b], next[b]);
+    if corner(a, b, c) != 0.0 {
+        indices.extend_from_slice(&[a, b, c]);
+    }
+    indices
+}
+
+/// Streams triangles given by indices into vertices.
+///
+/// Uses three indices per triangle.
+/// Each chunk contains whole triangles and never exceeds
+/// `BACK_END_MAX_VERTEX_COUNT` vertices.
+pub fn stream_indexed_tri_list<F>(m: Matrix2d, vertices: &[Vec2d], indices: &[usize], mut f: F)
+    where F: FnMut(&[[f32; 2]])
+{
+    let mut buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+    let max = BUFFER_SIZE - BUFFER_SIZE % 3;
+    for chunk in indices.chunks(max) {
+        let chunk = &chunk[..chunk.len() - chunk.len() % 3];
+        for (out, &ind) in buffer.iter_mut().zip(chunk) {
+            let p = vertices[ind];
+            *out = [tx(m, p[0], p[1]), ty(m, p[0], p[1])];
+        }
+        if !chunk.is_empty() {
+            f(&buffer[..chunk.len()]);
+        }
+    }
+}
+
+/// Splits a shape made of multiple contours into triangles.
+///
+/// The contours can overlap and intersect themselves.
+/// The fill rule decides which areas are inside, such that holes
+/// can be made by adding contours within another contour.
+///
+/// The shape is cut into horizontal slabs at every vertex and intersection.
+/// Within a slab, the edges do not cross, so the inside areas are trapezoids.
+pub fn with_contours_tri_list<F>(fill_rule: FillRule, m: Matrix2d, contours: Polygons, mut f: F)
+    where F: FnMut(&[[f32; 2]])
+{
+    // Edges going downwards have direction 1, upwards -1.
+    // Horizontal edges do not contribute to the winding number.
+    // Edges with infinite or NaN coordinates are skipped.
+    let mut edges: Vec<(Vec2d, Vec2d, i32)> = vec![];
+    for contour in contours {
+        let n = contour.len();
+        if n < 3 {
+            continue;
+        }
+        for i in 0..n {
+            let (a, b) = (contour[i], contour[(i + 1) % n]);
+            if !a.iter().chain(&b).all(|v| v.is_finite()) {
+                continue;
+            }
+            if a[1] < b[1] {
+                edges.push((a, b, 1));
+            } else if a[1] > b[1] {
+                edges.push((b, a, -1));
+            }
+        }
+    }
+
+    // Sorted by their top, the edges overlapping an edge vertically follow it.
+    edges.sort_by(|a, b| a.0[1].partial_cmp(&b.0[1]).unwrap_or(Ordering::Equal));
+    let mut ys: Vec<Scalar> = edges.iter().flat_map(|e| vec![e.0[1], e.1[1]]).collect();
+    for (i, a) in edges.iter().enumerate() {
+        for b in edges[i + 1..].iter().take_while(|b| b.0[1] < a.1[1]) {
+            if let Some(y) = edge_intersection_y(a.0, a.1, b.0, b.1) {
+                ys.push(y);
+            }
+        }
+    }
+    ys.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    ys.dedup();
+
+    let x_at = |e: &(Vec2d, Vec2d, i32), y: Scalar| -> Scalar {
+        let (top, bottom) = (e.0, e.1);
+        top[0] + (y - top[1]) / (bottom[1] - top[1]) * (bottom[0] - top[0])
+    };
+
+    let mut buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+    let max = BUFFER_SIZE - BUFFER_SIZE % 6;
     let mut i = 0;
-    stream_polygon_tri_list(m,
-                            || {
-        if i >= n {
-            return None;
+    // The edges spanning the slab.
+    let mut active: Vec<&(Vec2d, Vec2d, i32)> = vec![];
+    let mut next_edge = 0;
+    // Edges spanning the slab as `(x at top, x at bottom, direction)`.
+    let mut spans: Vec<(Scalar, Scalar, i32)> = vec![];
+    for w in ys.windows(2) {
+        let (y0, y1) = (w[0], w[1]);
+        while next_edge < edges.len() && edges[next_edge].0[1] <= y0 {
+            active.push(&edges[next_edge]);
+            next_edge += 1;
         }
+        active.retain(|e| e.1[1] >= y1);
+        spans.clear();
+        spans.extend(active.iter().map(|e| (x_at(e, y0), x_at(e, y1), e.2)));
+        spans.sort_by(|a, b| (a.0 + a.1).partial_cmp(&(b.0 + b.1)).unwrap_or(Ordering::Equal));
 
-        let j = i;
-        i += 1;
-        Some(polygon[j])
-    },
-                            f);
+        let mut winding = 0;
+        for j in 1..spans.len() {
+            winding += spans[j - 1].2;
+            if !fill_rule.is_inside(winding) {
+                continue;
+            }
+            let (left, right) = (spans[j - 1], spans[j]);
+            if left.0 == right.0 && left.1 == right.1 {
+                continue;
+            }
+            let quad = [[left.0, y0], [right.0, y0], [right.1, y1], [left.1, y1]];
+            for &k in &[0, 1, 2, 0, 2, 3] {
+                let p = quad[k];
+                buffer[i] = [tx(m, p[0], p[1]), ty(m, p[0], p[1])];
+                i += 1;
+            }
+            if i == max {
+                f(&buffer[..i]);
+                i = 0;
+            }
+        }
+    }
+    if i > 0 {
+        f(&buffer[..i]);
+    }
+}
+
+/// Returns the y coordinate where two edges cross, if they cross
+/// strictly between their end points.
+fn edge_intersection_y(a: Vec2d, b: Vec2d, c: Vec2d, d: Vec2d) -> Option<Scalar> {
+    let r = [b[0] - a[0], b[1] - a[1]];
+    let s = [d[0] - c[0], d[1] - c[1]];
+    let denom = r[0] * s[1] - r[1] * s[0];
+    if denom == 0.0 {
+        return None;
+    }
+    let q = [c[0] - a[0], c[1] - a[1]];
+    let t = (q[0] * s[1] - q[1] * s[0]) / denom;
+    let u = (q[0] * r[1] - q[1] * r[0]) / denom;
+    if t > 0.0 && t < 1.0 && u > 0.0 && u < 1.0 {
+        Some(a[1] + t * r[1])
+    } else {
+        None
+    }
+}
+
+/// Splits indexed triangles into chunks with `u16` indices.
+///
+/// Calls back with the original indices of the vertices in each chunk,
+/// and the triangle indices into those vertices.
+/// The number of vertices and the number of indices per chunk
+/// never exceed `BACK_END_MAX_VERTEX_COUNT`.
+/// This is suitable for `Graphics::tri_list_indexed`.
+pub fn chunk_indexed_tri_list<F>(vertex_count: usize, indices: &[usize], mut f: F)
+    where F: FnMut(&[usize], &[u16])
+{
+    const NONE: u16 = !0;
+
+    let max = BUFFER_SIZE - BUFFER_SIZE % 3;
+    let mut local = vec![NONE; vertex_count];
+    let mut vertices: Vec<usize> = Vec::with_capacity(BUFFER_SIZE);
+    let mut chunk: Vec<u16> = Vec::with_capacity(max);
+    for tri in indices.chunks(3).filter(|tri| tri.len() == 3) {
+        let new = tri.iter().filter(|&&i| local[i] == NONE).count();
+        if vertices.len() + new > BUFFER_SIZE || chunk.len() + 3 > max {
+            f(&vertices, &chunk);
+            for &i in &vertices {
+                local[i] = NONE;
+            }
+            vertices.clear();
+            chunk.clear();
+        }
+        for &i in tri {
+            if local[i] == NONE {
+                local[i] = vertices.len() as u16;
+                vertices.push(i);
+            }
+            chunk.push(local[i]);
+        }
+    }
+    if !chunk.is_empty() {
+        f(&vertices, &chunk);
+    }
+}
+
+/// The indices of the two triangles of a rectangle
+/// with corners from `rect_quad_xy`.
+pub const RECT_QUAD_INDICES: [u16; 6] = [0, 1, 2, 1, 3, 2];
+
+/// Creates the corners of a rectangle.
+///
+/// The corners are ordered top left, top right, bottom left and bottom right,
+/// which is used by `RECT_QUAD_INDICES`.
+#[inline(always)]
+pub fn rect_quad_xy(m: Matrix2d, rect: Rectangle) -> [[f32; 2]; 4] {
+    let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
+    let (x2, y2) = (x + w, y + h);
+    [[tx(m, x, y), ty(m, x, y)],
+     [tx(m, x2, y), ty(m, x2, y)],
+     [tx(m, x, y2), ty(m, x, y2)],
+     [tx(m, x2, y2), ty(m, x2, y2)]]
+}
+
+/// Creates the texture coords of the corners of an image.
+///
+/// Uses the same order as `rect_quad_xy`.
+#[inline(always)]
+pub fn rect_quad_uv<I: ImageSize>(image: &I, source_rect: SourceRectangle) -> [[f32; 2]; 4] {
+    let uv = rect_tri_list_uv(image, source_rect);
+    [uv[0], uv[1], uv[2], uv[4]]
+}
+
+/// Streams a list of triangles.
+///
+/// Each chunk contains whole triangles and never exceeds
+/// `BACK_END_MAX_VERTEX_COUNT` vertices.
+pub fn stream_tri_list<F>(m: Matrix2d, vertices: &[Vec2d], mut f: F)
+    where F: FnMut(&[[f32; 2]])
+{
+    let mut buffer: [[f32; 2]; BUFFER_SIZE] = [[0.0; 2]; BUFFER_SIZE];
+    let max = BUFFER_SIZE - BUFFER_SIZE % 3;
+    for chunk in vertices.chunks(max) {
+        let chunk = &chunk[..chunk.len() - chunk.len() % 3];
+        for (out, p) in buffer.iter_mut().zip(chunk) {
+            *out = [tx(m, p[0], p[1]), ty(m, p[0], p[1])];
+        }
+        if !chunk.is_empty() {
+            f(&buffer[..chunk.len()]);
+        }
+    }
+}
+
+/// The number of triangles used for half a circle in polyline caps and joins.
+const POLYLINE_ROUND_RESOLUTION: usize = 32;
+
+/// Computes triangles of a stroked polyline.
+///
+/// Returns three vertices per triangle.
+/// The cap shape is used at the ends of open polylines,
+/// where `Shape::Square` ends the line at the end point.
+/// The join decides the shape of the outer side of corners.
+///
+/// Triangles do not overlap, such that semi-transparent colors blend
+/// evenly, except at corners where the line is too short for
+/// the inner sides of the segments to meet.
+/// Repeated points are ignored.
+pub fn polyline_triangles(cap: Shape,
+                          join: Join,
+                          closed: bool,
+                          points: &[Vec2d],
+                          radius: Radius)
+                          -> Vec<Vec2d> {
+    use std::f64::consts::PI;
+
+    let mut points: Vec<Vec2d> = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    let n = points.len();
+    let mut res = vec![];
+    if n == 1 && !closed {
+        // Draw a dot with round caps.
+        if let Shape::Round = cap {
+            let p = points[0];
+            let [px, py] = p;
+            arc_fan(&mut res, p, p, [px + radius, py], radius, 2.0 * PI);
+        }
+        return res;
+    }
+    if n < 2 || (closed && n < 3) {
+        return res;
+    }
+
+    let add = |a: Vec2d, b: Vec2d, k: Scalar| [a[0] + b[0] * k, a[1] + b[1] * k];
+    let segments = if closed { n } else { n - 1 };
+    // Directions and lengths of segments.
+    let mut dirs = Vec::with_capacity(segments);
+    let mut lens = Vec::with_capacity(segments);
+    for i in 0..segments {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        dirs.push([dx / len, dy / len]);
+        lens.push(len);
+    }
+    let left = |d: Vec2d| [-d[1], d[0]];
+    let arc = |res: &mut Vec<Vec2d>, fan: Vec2d, center: Vec2d, from: Vec2d, angle: Scalar| {
+        arc_fan(res, fan, center, from, radius, angle)
+    };
+
+    // The left and right end points of each segment at its start and end.
+    let mut starts = vec![[[0.0; 2]; 2]; segments];
+    let mut ends = vec![[[0.0; 2]; 2]; segments];
+    for i in 0..segments {
+        let d = dirs[i];
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        starts[i] = [add(a, left(d), radius), add(a, left(d), -radius)];
+        ends[i] = [add(b, left(d), radius), add(b, left(d), -radius)];
+    }
+
+    let joints = if closed { 0..n } else { 1..n - 1 };
+    for j in joints {
+        let (i0, i1) = ((j + segments - 1) % segments, j);
+        let p = points[j];
+        let (d0, d1) = (dirs[i0], dirs[i1]);
+        let cross = d0[0] * d1[1] - d0[1] * d1[0];
+        let dot = d0[0] * d1[0] + d0[1] * d1[1];
+        if cross.abs() < 1e-9 && dot > 0.0 {
+            continue;
+        }
+        // The inner side is the left side when turning left.
+        let s = if cross > 0.0 { 1.0 } else { -1.0 };
+        let side = if cross > 0.0 { 0 } else { 1 };
+        let o0 = add(p, left(d0), -s * radius);
+        let o1 = add(p, left(d1), -s * radius);
+        // The distance from the corner to where the inner sides meet,
+        // along each segment.
+        let t = radius * cross.abs() / (1.0 + dot);
+        let center = if dot > -1.0 + 1e-9 && t <= 0.5 * lens[i0].min(lens[i1]) {
+            let inner = add(add(p, left(d0), s * radius), d0, -t);
+            ends[i0][side] = inner;
+            starts[i1][side] = inner;
+            inner
+        } else {
+            p
+        };
+
+        let half_cos = (0.5 * (1.0 + dot)).max(0.0).sqrt();
+        match join {
+            Join::Miter(limit) if half_cos > 0.0 && 1.0 / half_cos <= limit => {
+                let bisector = [o0[0] + o1[0] - 2.0 * p[0], o0[1] + o1[1] - 2.0 * p[1]];
+                let len = (bisector[0] * bisector[0] + bisector[1] * bisector[1]).sqrt();
+                let tip = add(p, bisector, radius / (half_cos * len));
+                res.extend_from_slice(&[center, o0, tip, center, tip, o1]);
+            }
+            Join::Round => {
+                // `clamp` needs Rust 1.50.
+                #[allow(clippy::manual_clamp)]
+                let angle = dot.max(-1.0).min(1.0).acos();
+                arc(&mut res, center, p, o0, if cross < 0.0 { -angle } else { angle });
+            }
+            _ => res.extend_from_slice(&[center, o0, o1]),
+        }
+    }
+
+    for i in 0..segments {
+        let ([sl, sr], [el, er]) = (starts[i], ends[i]);
+        res.extend_from_slice(&[sl, sr, er, sl, er, el]);
+    }
+
+    if !closed {
+        let (first, last) = (points[0], points[n - 1]);
+        let (d0, d1) = (dirs[0], dirs[segments - 1]);
+        let ([sl, sr], [el, er]) = (starts[0], ends[segments - 1]);
+        match cap {
+            Shape::Square => {}
+            Shape::Round => {
+                arc(&mut res, first, first, sl, PI);
+                arc(&mut res, last, last, er, PI);
+            }
+            Shape::Bevel => {
+                res.extend_from_slice(&[sl, add(first, d0, -radius), sr]);
+                res.extend_from_slice(&[er, add(last, d1, radius), el]);
+            }
+        }
+    }
+    res
+}
+
+/// Adds a fan of triangles for a circle arc, starting at a point on the circle.
+fn arc_fan(res: &mut Vec<Vec2d>,
+           fan: Vec2d,
+           center: Vec2d,
+           from: Vec2d,
+           radius: Radius,
+           angle: Scalar) {
+    use std::f64::consts::PI;
+
+    let start = (from[1] - center[1]).atan2(from[0] - center[0]);
+    let steps = ((angle.abs() / PI * POLYLINE_ROUND_RESOLUTION as Scalar).ceil() as usize).max(1);
+    let mut prev = from;
+    for k in 1..steps + 1 {
+        let a = start + angle * k as Scalar / steps as Scalar;
+        let next = [center[0] + radius * a.cos(), center[1] + radius * a.sin()];
+        res.extend_from_slice(&[fan, prev, next]);
+        prev = next;
+    }
+}
+
+/// Computes the outline of a rectangle with round corners.
+///
+/// Uses the same points as `with_round_rectangle_tri_list`.
+pub fn round_rectangle_outline(resolution_corner: Resolution,
+                               rect: Rectangle,
+                               round_radius: Radius)
+                               -> Vec<Vec2d> {
+    let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
+    let radius = round_radius;
+    let centers = [[x + w - radius, y + h - radius],
+                   [x + radius, y + h - radius],
+                   [x + radius, y + radius],
+                   [x + w - radius, y + radius]];
+    let mut res = Vec::with_capacity(4 * resolution_corner as usize);
+    for (k, c) in centers.iter().enumerate() {
+        for j in 0..resolution_corner {
+            let angle = (j as Scalar / (resolution_corner - 1) as Scalar + k as Scalar) *
+                        <Scalar as Radians>::_90();
+            res.push([c[0] + angle.cos() * radius, c[1] + angle.sin() * radius]);
+        }
+    }
+    res
+}
+
+/// Computes the outline of an ellipse specified by a resolution.
+pub fn ellipse_outline(resolution: Resolution, rect: Rectangle) -> Vec<Vec2d> {
+    let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
+    let (cw, ch) = (0.5 * w, 0.5 * h);
+    let (cx, cy) = (x + cw, y + ch);
+    (0..resolution)
+        .map(|i| {
+            let angle = i as Scalar / resolution as Scalar * <Scalar as Radians>::_360();
+            [cx + angle.cos() * cw, cy + angle.sin() * ch]
+        })
+        .collect()
+}
+
+/// Streams a stroked polyline.
+///
+/// See `polyline_triangles` for details.
+pub fn with_polyline_tri_list<F>(cap: Shape,
+                                 join: Join,
+                                 closed: bool,
+                                 m: Matrix2d,
+                                 points: &[Vec2d],
+                                 radius: Radius,
+                                 f: F)
+    where F: FnMut(&[[f32; 2]])
+{
+    let vertices = polyline_triangles(cap, join, closed, points, radius);
+    stream_tri_list(m, &vertices, f);
+}
+
+/// Splits a triangle with a color per vertex into smaller triangles of solid color.
+///
+/// The number of triangles depends on the difference between the colors,
+/// such that a triangle with a single color is not split.
+/// Each small triangle uses the interpolated color at its center.
+pub fn with_subdivided_tri_c<F>(tri: [[f32; 2]; 3], colors: [[f32; 4]; 3], mut f: F)
+    where F: FnMut(&[[f32; 2]], &[f32; 4])
+{
+    with_subdivided_tri_uv_c(tri, [[0.0; 2]; 3], colors, |vertices, _, color| f(vertices, color));
+}
+
+/// Splits a textured triangle with a color per vertex into smaller triangles of solid color.
+///
+/// Works like `with_subdivided_tri_c`, interpolating the texture coordinates.
+pub fn with_subdivided_tri_uv_c<F>(tri: [[f32; 2]; 3],
+                                   uvs: [[f32; 2]; 3],
+                                   colors: [[f32; 4]; 3],
+                                   mut f: F)
+    where F: FnMut(&[[f32; 2]], &[[f32; 2]], &[f32; 4])
+{
+    let mut diff: f32 = 0.0;
+    for ((a, b), c) in colors[0].iter().zip(&colors[1]).zip(&colors[2]) {
+        diff = diff.max((a - b).abs()).max((b - c).abs()).max((c - a).abs());
+    }
+    // `clamp` needs Rust 1.50.
+    #[allow(clippy::manual_clamp)]
+    let n = ((diff * 16.0).ceil() as usize).max(1).min(16);
+    let lerp = |p: [[f32; 2]; 3], i: usize, j: usize| -> [f32; 2] {
+        let (u, v) = (i as f32 / n as f32, j as f32 / n as f32);
+        [p[0][0] + (p[1][0] - p[0][0]) * u + (p[2][0] - p[0][0]) * v,
+         p[0][1] + (p[1][1] - p[0][1]) * u + (p[2][1] - p[0][1]) * v]
+    };
+    let color = |u: f32, v: f32| -> [f32; 4] {
+        let w = 1.0 - u - v;
+        let mut res = [0.0; 4];
+        for (i, r) in res.iter_mut().enumerate() {
+            *r = w * colors[0][i] + u * colors[1][i] + v * colors[2][i];
+        }
+        res
+    };
+    let k = n as f32;
+    for i in 0..n {
+        for j in 0..n - i {
+            let (u, v) = (i as f32, j as f32);
+            f(&[lerp(tri, i, j), lerp(tri, i + 1, j), lerp(tri, i, j + 1)],
+              &[lerp(uvs, i, j), lerp(uvs, i + 1, j), lerp(uvs, i, j + 1)],
+              &color((u + 1.0 / 3.0) / k, (v + 1.0 / 3.0) / k));
+            if i + j + 1 < n {
+                f(&[lerp(tri, i + 1, j), lerp(tri, i + 1, j + 1), lerp(tri, i, j + 1)],
+                  &[lerp(uvs, i + 1, j), lerp(uvs, i + 1, j + 1), lerp(uvs, i, j + 1)],
+                  &color((u + 2.0 / 3.0) / k, (v + 2.0 / 3.0) / k));
+            }
+        }
+    }
 }
 
 /// Creates triangle list vertices from rectangle.
@@ -644,3 +1235,191 @@ pub fn rect_tri_list_uv<I: ImageSize>(image: &I, source_rect: SourceRectangle) -
     let y2 = (src_h + src_y) as f32 / h as f32;
     [[x1, y1], [x2, y1], [x1, y2], [x2, y1], [x2, y2], [x1, y2]]
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::{area, identity};
+
+    fn chunk_area(vertices: &[[f32; 2]]) -> Scalar {
+        assert!(vertices.len() <= BUFFER_SIZE);
+        assert_eq!(vertices.len() % 3, 0);
+        let mut sum = 0.0;
+        for t in vertices.chunks(3) {
+            let t = [[t[0][0] as Scalar, t[0][1] as Scalar],
+                     [t[1][0] as Scalar, t[1][1] as Scalar],
+                     [t[2][0] as Scalar, t[2][1] as Scalar]];
+            sum += area(&t).abs();
+        }
+        sum
+    }
+
+    fn tri_list_area(polygon: Polygon) -> Scalar {
+        let mut sum = 0.0;
+        with_polygon_tri_list(identity(), polygon, |vertices| sum += chunk_area(vertices));
+        sum
+    }
+
+    fn contours_area(fill_rule: FillRule, contours: Polygons) -> Scalar {
+        let mut sum = 0.0;
+        with_contours_tri_list(fill_rule, identity(), contours, |vertices| {
+            sum += chunk_area(vertices)
+        });
+        sum
+    }
+
+    #[test]
+    fn test_concave_polygon() {
+        // An arrow pointing to the right.
+        let arrow = [[0.0, 1.0], [2.0, 1.0], [2.0, 0.0], [4.0, 2.0], [2.0, 4.0], [2.0, 3.0],
+                     [0.0, 3.0]];
+        assert_eq!(tri_list_area(&arrow), area(&arrow).abs());
+        let mut reversed = arrow;
+        reversed.reverse();
+        assert_eq!(tri_list_area(&reversed), area(&arrow).abs());
+    }
+
+    #[test]
+    fn test_convex() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 2.0], [2.0, 2.0], [0.0, 2.0]];
+        assert!(is_convex(&square));
+        let arrow = [[0.0, 1.0], [2.0, 1.0], [2.0, 0.0], [4.0, 2.0], [2.0, 4.0], [2.0, 3.0],
+                     [0.0, 3.0]];
+        assert!(!is_convex(&arrow));
+        // All corners of a pentagram turn the same way, going around twice.
+        let star: Vec<Vec2d> = (0..5)
+            .map(|i| {
+                let angle = (2 * i) as Scalar / 5.0 * <Scalar as Radians>::_360();
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        assert!(!is_convex(&star));
+    }
+
+    #[test]
+    fn test_many_reflex_corners() {
+        // A star with every other corner pointing inwards.
+        let n = 4000;
+        let star: Vec<Vec2d> = (0..n)
+            .map(|i| {
+                let angle = i as Scalar / n as Scalar * <Scalar as Radians>::_360();
+                let radius = if i % 2 == 0 { 1.0 } else { 0.9 };
+                [radius * angle.cos(), radius * angle.sin()]
+            })
+            .collect();
+        assert_eq!(triangulate_polygon(&star).len(), 3 * (n - 2));
+        assert!((tri_list_area(&star) - area(&star)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_degenerate_polygon() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 2.0], [2.0, 2.0], [0.0, 2.0]];
+        assert_eq!(triangulate_polygon(&square).len(), 9);
+        assert_eq!(tri_list_area(&square), 4.0);
+        assert!(triangulate_polygon(&[[0.0, 0.0], [1.0, 1.0]]).is_empty());
+    }
+
+    #[test]
+    fn test_chunks() {
+        let n = 1000;
+        let circle: Vec<Vec2d> = (0..n)
+            .map(|i| {
+                let angle = i as Scalar / n as Scalar * <Scalar as Radians>::_360();
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        assert!((tri_list_area(&circle) - area(&circle)).abs() < 1e-6);
+    }
+
+    #[test]
+    // The associated float constants need Rust 1.43.
+    #[allow(clippy::legacy_numeric_constants)]
+    fn test_contours_nan() {
+        use std::f64::NAN;
+
+        let square = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let broken = [[1.0, 1.0], [NAN, 1.0], [3.0, 3.0], [1.0, 3.0]];
+        // Only the left edge of the broken contour is left, inside the square.
+        assert_eq!(contours_area(FillRule::NonZero, &[&square, &broken]), 16.0);
+        assert_eq!(contours_area(FillRule::NonZero, &[&square, &[[NAN; 2]; 3]]), 16.0);
+    }
+
+    #[test]
+    fn test_contours_hole() {
+        let outer = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let same = [[1.0, 1.0], [3.0, 1.0], [3.0, 3.0], [1.0, 3.0]];
+        let opposite = [[1.0, 1.0], [1.0, 3.0], [3.0, 3.0], [3.0, 1.0]];
+        assert_eq!(contours_area(FillRule::EvenOdd, &[&outer, &same]), 12.0);
+        assert_eq!(contours_area(FillRule::EvenOdd, &[&outer, &opposite]), 12.0);
+        assert_eq!(contours_area(FillRule::NonZero, &[&outer, &same]), 16.0);
+        assert_eq!(contours_area(FillRule::NonZero, &[&outer, &opposite]), 12.0);
+    }
+
+    #[test]
+    fn test_contours_self_intersecting() {
+        // A pentagram, where the center has winding number 2.
+        let star: Vec<Vec2d> = (0..5)
+            .map(|i| {
+                let angle = (i * 2) as Scalar / 5.0 * <Scalar as Radians>::_360();
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        let nonzero = contours_area(FillRule::NonZero, &[&star]);
+        let evenodd = contours_area(FillRule::EvenOdd, &[&star]);
+        // The center is a regular pentagon with circumradius cos(72°) / cos(36°).
+        let r = (72.0 as Scalar).to_radians().cos() / (36.0 as Scalar).to_radians().cos();
+        let pentagon = 2.5 * r * r * (72.0 as Scalar).to_radians().sin();
+        assert!((nonzero - evenodd - pentagon).abs() < 1e-6);
+    }
+
+    fn polyline_area(cap: Shape, join: Join, closed: bool, points: &[Vec2d]) -> Scalar {
+        let vertices = polyline_triangles(cap, join, closed, points, 1.0);
+        vertices.chunks(3).map(|t| area(&[t[0], t[1], t[2]]).abs()).sum()
+    }
+
+    #[test]
+    fn test_polyline_joins() {
+        let corner = [[0.0, 0.0], [10.0, 0.0], [10.0, 0.0], [10.0, 10.0]];
+        let miter = polyline_area(Shape::Square, Join::Miter(2.0), false, &corner);
+        assert!((miter - 40.0).abs() < 1e-9);
+        // The miter of a right angle exceeds a limit below the square root of 2.
+        let limited = polyline_area(Shape::Square, Join::Miter(1.4), false, &corner);
+        assert!((limited - 39.5).abs() < 1e-9);
+        let bevel = polyline_area(Shape::Square, Join::Bevel, false, &corner);
+        assert!((bevel - 39.5).abs() < 1e-9);
+        let round = polyline_area(Shape::Square, Join::Round, false, &corner);
+        assert!(round > 39.5 && round < 39.0 + <Scalar as Radians>::_90() / 2.0);
+        let reversed = [[10.0, 10.0], [10.0, 0.0], [0.0, 0.0]];
+        assert_eq!(polyline_area(Shape::Square, Join::Bevel, false, &reversed), bevel);
+    }
+
+    #[test]
+    fn test_polyline_caps() {
+        let line = [[0.0, 0.0], [10.0, 0.0]];
+        assert_eq!(polyline_area(Shape::Square, Join::Bevel, false, &line), 20.0);
+        assert!((polyline_area(Shape::Bevel, Join::Bevel, false, &line) - 22.0).abs() < 1e-9);
+        let round = polyline_area(Shape::Round, Join::Bevel, false, &line);
+        assert!(round > 22.0 && round < 20.0 + <Scalar as Radians>::_180());
+    }
+
+    #[test]
+    fn test_polyline_closed() {
+        let square = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        let area = polyline_area(Shape::Round, Join::Miter(2.0), true, &square);
+        assert!((area - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_chunk_indexed_tri_list() {
+        // A strip of triangles sharing vertices.
+        let n = 2000;
+        let indices: Vec<usize> = (0..n).flat_map(|i| vec![i, i + 1, i + 2]).collect();
+        let mut triangles = vec![];
+        chunk_indexed_tri_list(n + 2, &indices, |vertices, chunk| {
+            assert!(vertices.len() <= BUFFER_SIZE);
+            assert!(chunk.len() <= BUFFER_SIZE);
+            triangles.extend(chunk.iter().map(|&i| vertices[i as usize]));
+        });
+        assert_eq!(triangles, indices);
+    }
+}